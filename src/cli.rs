@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::path::PathBuf;
+
+use gtk::glib::ExitCode;
+use strum::IntoEnumIterator;
+
+use crate::data::{
+    compression::Compression,
+    file_format::PaletteFile,
+    image,
+    list_items::{BGModeTwo, Bpp, TileSize, TilemapSize},
+    palette::Palette,
+    render::{self, ColorZero, RenderOptions},
+    tilemap::Tilemap,
+    tiles::Tileset,
+};
+
+const USAGE: &str = "\
+Usage:
+  waffle                 open the editor
+  waffle palette <input> <output> [--from FORMAT] [--to FORMAT]
+                 [--in-compression C] [--out-compression C]
+  waffle tileset <input> <output> --from BPP --to BPP
+                 [--in-compression C] [--out-compression C]
+  waffle render <palette> <tileset> <tilemap> <output.png>
+                [--bpp BPP] [--size SIZE] [--tile-size SIZE] [--bg-mode MODE]
+                [--transparent] [--palette-format FORMAT]
+                [--palette-compression C] [--tileset-compression C] [--tilemap-compression C]
+  waffle import <input.png> <palette> <tileset> <tilemap>
+                [--bpp BPP] [--bg-mode MODE] [--base-palette FILE] [--palette-format FORMAT]
+                [--palette-compression C] [--tileset-compression C] [--tilemap-compression C]
+
+  FORMAT  bgr555 (default), rgb24, jasc, gpl, act, hex
+  BPP     2bpp, 4bpp (default), 8bpp
+  SIZE    32x32 (default), 64x32, 32x64, 64x64 for --size, 8x8 (default), 16x16 for --tile-size
+  MODE    M0BG1 (default), M0BG2, M0BG3, M0BG4, only used for 2bpp
+  C       none (default), lz1, lz2, rle";
+
+// run a subcommand without opening a window, None if the arguments aren't a subcommand
+pub fn run(args: &[String]) -> Option<ExitCode> {
+    let (cmd, rest) = args.split_first()?;
+    let result = match cmd.as_str() {
+        "palette" => convert_palette(rest),
+        "tileset" => convert_tileset(rest),
+        "render" => render(rest),
+        "import" => import(rest),
+        "help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => return None,
+    };
+    match result {
+        Ok(_) => Some(ExitCode::SUCCESS),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
+// positional arguments, "--name value" options and "--name" switches
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl Args {
+    fn parse(args: &[String], options: &[&str], switches: &[&str]) -> std::io::Result<Self> {
+        let mut res = Self {
+            positional: Vec::new(),
+            options: HashMap::new(),
+            switches: HashSet::new(),
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                res.positional.push(arg.clone());
+                continue;
+            };
+            if switches.contains(&name) {
+                res.switches.insert(name.to_string());
+            } else if options.contains(&name) {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| invalid(format!("{arg} needs a value")))?;
+                res.options.insert(name.to_string(), value.clone());
+            } else {
+                return Err(invalid(format!("unknown option {arg}\n{USAGE}")));
+            }
+        }
+        Ok(res)
+    }
+
+    fn files<const N: usize>(&self) -> std::io::Result<[PathBuf; N]> {
+        if self.positional.len() != N {
+            return Err(invalid(format!(
+                "expected {N} files but got {}\n{USAGE}",
+                self.positional.len()
+            )));
+        }
+        Ok(std::array::from_fn(|i| PathBuf::from(&self.positional[i])))
+    }
+
+    fn path(&self, name: &str) -> Option<PathBuf> {
+        self.options.get(name).map(PathBuf::from)
+    }
+
+    // matches the displayed name or the variant name, ignoring case
+    fn value<T: IntoEnumIterator + Debug + Display>(
+        &self,
+        name: &str,
+        default: Option<T>,
+    ) -> std::io::Result<T> {
+        let Some(s) = self.options.get(name) else {
+            return default.ok_or_else(|| invalid(format!("--{name} is required")));
+        };
+        T::iter()
+            .find(|v| {
+                s.eq_ignore_ascii_case(&v.to_string()) || s.eq_ignore_ascii_case(&format!("{v:?}"))
+            })
+            .ok_or_else(|| {
+                let choices = T::iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                invalid(format!(
+                    "invalid value {s} for --{name}, expected one of {}",
+                    choices.join(", ")
+                ))
+            })
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.contains(name)
+    }
+}
+
+fn convert_palette(args: &[String]) -> std::io::Result<()> {
+    let args = Args::parse(
+        args,
+        &["from", "to", "in-compression", "out-compression"],
+        &[],
+    )?;
+    let [input, output] = args.files()?;
+    let palette = Palette::from_file_format(
+        &input,
+        args.value("from", Some(PaletteFile::default()))?,
+        args.value("in-compression", Some(Compression::None))?,
+    )?;
+    palette.write_file_format(
+        &File::create(&output)?,
+        args.value("to", Some(PaletteFile::default()))?,
+        args.value("out-compression", Some(Compression::None))?,
+    )?;
+    println!("convert palette: {input:?} -> {output:?}");
+    Ok(())
+}
+
+fn convert_tileset(args: &[String]) -> std::io::Result<()> {
+    let args = Args::parse(
+        args,
+        &["from", "to", "in-compression", "out-compression"],
+        &[],
+    )?;
+    let [input, output] = args.files()?;
+    let from: Bpp = args.value("from", None)?;
+    let to: Bpp = args.value("to", None)?;
+    let content = args
+        .value("in-compression", Some(Compression::None))?
+        .read_file(&input)?;
+    let tileset = Tileset::from_bytes(&content, from)?;
+    tileset.check_bpp(to)?;
+    args.value("out-compression", Some(Compression::None))?
+        .write_file(&File::create(&output)?, &tileset.to_bytes(to))?;
+    println!(
+        "convert tileset: {input:?} -> {output:?}, {} tiles from {from} to {to}",
+        tileset.0.len()
+    );
+    Ok(())
+}
+
+fn render(args: &[String]) -> std::io::Result<()> {
+    let args = Args::parse(
+        args,
+        &[
+            "bpp",
+            "size",
+            "tile-size",
+            "bg-mode",
+            "palette-format",
+            "palette-compression",
+            "tileset-compression",
+            "tilemap-compression",
+        ],
+        &["transparent"],
+    )?;
+    let [palette_file, tileset_file, tilemap_file, output] = args.files()?;
+    let bpp = args.value("bpp", Some(Bpp::Four))?;
+    let bg_mode: BGModeTwo = args.value("bg-mode", Some(BGModeTwo::default()))?;
+
+    let palette = Palette::from_file_format(
+        &palette_file,
+        args.value("palette-format", Some(PaletteFile::default()))?,
+        args.value("palette-compression", Some(Compression::None))?,
+    )?;
+    let content = args
+        .value("tileset-compression", Some(Compression::None))?
+        .read_file(&tileset_file)?;
+    let tileset = Tileset::from_bytes(&content, bpp)?;
+    let content = args
+        .value("tilemap-compression", Some(Compression::None))?
+        .read_file(&tilemap_file)?;
+    let tilemap = Tilemap::from_bytes(&content, args.value("size", Some(TilemapSize::default()))?)?;
+
+    let options = RenderOptions {
+        bpp,
+        palette_base: bpp.palette_base(bg_mode) as usize,
+        tile_size: args.value("tile-size", Some(TileSize::default()))?,
+        color_zero: if args.switch("transparent") {
+            ColorZero::Transparent
+        } else {
+            ColorZero::Backdrop
+        },
+        mark_missing: false,
+    };
+    let image = render::render_tilemap(&tileset, &palette, &tilemap, options);
+    image.write_png(&File::create(&output)?)?;
+    println!("render tilemap: {output:?}");
+    Ok(())
+}
+
+fn import(args: &[String]) -> std::io::Result<()> {
+    let args = Args::parse(
+        args,
+        &[
+            "bpp",
+            "bg-mode",
+            "base-palette",
+            "palette-format",
+            "palette-compression",
+            "tileset-compression",
+            "tilemap-compression",
+        ],
+        &[],
+    )?;
+    let [input, palette_file, tileset_file, tilemap_file] = args.files()?;
+    let bpp = args.value("bpp", Some(Bpp::Four))?;
+    let bg_mode: BGModeTwo = args.value("bg-mode", Some(BGModeTwo::default()))?;
+    let palette_format = args.value("palette-format", Some(PaletteFile::default()))?;
+    let palette_compression = args.value("palette-compression", Some(Compression::None))?;
+
+    // colors outside the imported subpalettes are kept from the base palette
+    let base = match args.path("base-palette") {
+        Some(path) => Palette::from_file_format(&path, palette_format, palette_compression)?,
+        None => Palette::default(),
+    };
+    let (tileset, tilemap, palette) =
+        image::import_png(&input, bpp, bpp.palette_base(bg_mode) as usize, base)?;
+
+    palette.write_file_format(
+        &File::create(&palette_file)?,
+        palette_format,
+        palette_compression,
+    )?;
+    args.value("tileset-compression", Some(Compression::None))?
+        .write_file(&File::create(&tileset_file)?, &tileset.to_bytes(bpp))?;
+    args.value("tilemap-compression", Some(Compression::None))?
+        .write_file(&File::create(&tilemap_file)?, &tilemap.to_bytes())?;
+    println!("import png: {input:?}, {} unique tiles", tileset.0.len());
+    Ok(())
+}
@@ -15,6 +15,20 @@ impl Color {
         (self.red(), self.green(), self.blue())
     }
 
+    pub fn from_rgb24(r: u8, g: u8, b: u8) -> Self {
+        // keep the top 5 bits
+        Self::new()
+            .with_red(r >> 3)
+            .with_green(g >> 3)
+            .with_blue(b >> 3)
+    }
+
+    pub fn to_rgb24(&self) -> (u8, u8, u8) {
+        // convert range 0-31 to 0-255
+        let (r, g, b) = self.to_tuple();
+        (r << 3 | r >> 2, g << 3 | g >> 2, b << 3 | b >> 2)
+    }
+
     pub fn to_cairo(&self) -> (f64, f64, f64) {
         // convert range 0-31 to 0.0-1.0
         (
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+use gtk::glib;
+use strum::{EnumIter, EnumString};
+
+// compression applied to a whole palette, tileset or tilemap file
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone, glib::Enum)]
+#[enum_type(name = "Compression")]
+pub enum Compression {
+    #[default]
+    #[enum_value(nick = "none")]
+    None,
+    // LZ77 with RLE commands, little endian copy address
+    #[enum_value(nick = "lz1")]
+    LZ1,
+    // same as LZ1 with big endian copy address
+    #[enum_value(nick = "lz2")]
+    LZ2,
+    #[enum_value(nick = "rle")]
+    RLE,
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::LZ1 => write!(f, "lz1"),
+            Compression::LZ2 => write!(f, "lz2"),
+            Compression::RLE => write!(f, "rle"),
+        }
+    }
+}
+
+impl Compression {
+    pub fn read_file(&self, path: &std::path::PathBuf) -> std::io::Result<Vec<u8>> {
+        self.decompress(&std::fs::read(path)?)
+    }
+
+    pub fn write_file(&self, mut f: &File, data: &[u8]) -> std::io::Result<()> {
+        f.write_all(&self.compress(data))
+    }
+
+    // data after the end marker is ignored
+    pub fn decompress(&self, content: &[u8]) -> std::io::Result<Vec<u8>> {
+        Ok(self.decompress_len(content)?.0)
+    }
+
+    // also return the number of bytes read, including the end marker
+    pub fn decompress_len(&self, content: &[u8]) -> std::io::Result<(Vec<u8>, usize)> {
+        match self {
+            Compression::None => Ok((content.to_vec(), content.len())),
+            Compression::LZ1 => lz_decompress(content, false),
+            Compression::LZ2 => lz_decompress(content, true),
+            Compression::RLE => rle_decompress(content),
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::None => data.to_vec(),
+            Compression::LZ1 => lz_compress(data, false),
+            Compression::LZ2 => lz_compress(data, true),
+            Compression::RLE => rle_compress(data),
+        }
+    }
+}
+
+fn truncated() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "compressed data ends unexpectedly",
+    )
+}
+
+// lunar compress LC_LZ1/LC_LZ2
+// header byte CCCLLLLL, or 111CCCLL LLLLLLLL for long lengths, 0xFF ends the data
+// command 0: copy L+1 bytes, 1: byte fill, 2: word fill, 3: increasing fill,
+// 4: repeat L+1 bytes from an address in the output
+const LZ_END: u8 = 0xFF;
+const LZ_COPY: u8 = 0;
+const LZ_BYTE_FILL: u8 = 1;
+const LZ_WORD_FILL: u8 = 2;
+const LZ_INC_FILL: u8 = 3;
+const LZ_REPEAT: u8 = 4;
+// max length of a long command
+const LZ_MAX_LEN: usize = 1024;
+
+fn lz_decompress(content: &[u8], big_endian: bool) -> std::io::Result<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    let mut next = || {
+        let b = content.get(i).copied().ok_or_else(truncated);
+        i += 1;
+        b
+    };
+    loop {
+        let header = next()?;
+        if header == LZ_END {
+            break;
+        }
+        let (cmd, len) = if header >> 5 == 7 {
+            let lo = next()?;
+            (
+                (header >> 2) & 0b111,
+                ((header as usize & 0b11) << 8 | lo as usize) + 1,
+            )
+        } else {
+            (header >> 5, (header as usize & 0b11111) + 1)
+        };
+
+        match cmd {
+            LZ_COPY => {
+                for _ in 0..len {
+                    out.push(next()?);
+                }
+            }
+            LZ_BYTE_FILL => {
+                let b = next()?;
+                out.extend(std::iter::repeat_n(b, len));
+            }
+            LZ_WORD_FILL => {
+                let w = [next()?, next()?];
+                out.extend((0..len).map(|j| w[j % 2]));
+            }
+            LZ_INC_FILL => {
+                let b = next()?;
+                out.extend((0..len).map(|j| b.wrapping_add(j as u8)));
+            }
+            LZ_REPEAT => {
+                let (a, b) = (next()?, next()?);
+                let addr = if big_endian {
+                    u16::from_be_bytes([a, b])
+                } else {
+                    u16::from_le_bytes([a, b])
+                } as usize;
+                if addr >= out.len() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("repeat address {addr:#06X} is past the decompressed data"),
+                    ));
+                }
+                // source and destination may overlap
+                for j in 0..len {
+                    out.push(out[addr + j]);
+                }
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown command {cmd}"),
+                ))
+            }
+        }
+    }
+    Ok((out, i))
+}
+
+fn lz_header(out: &mut Vec<u8>, cmd: u8, len: usize) {
+    let l = len - 1;
+    if len > 32 {
+        out.push(0b111 << 5 | cmd << 2 | (l >> 8) as u8);
+        out.push(l as u8);
+    } else {
+        out.push(cmd << 5 | l as u8);
+    }
+}
+
+fn lz_flush_copy(out: &mut Vec<u8>, literal: &mut Vec<u8>) {
+    for chunk in literal.chunks(LZ_MAX_LEN) {
+        lz_header(out, LZ_COPY, chunk.len());
+        out.extend(chunk);
+    }
+    literal.clear();
+}
+
+// greedy: at each position take the command saving the most bytes
+fn lz_compress(data: &[u8], big_endian: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut literal = Vec::new();
+    // positions of every 2 byte sequence, for finding repeats
+    let mut prefixes: HashMap<[u8; 2], Vec<usize>> = HashMap::new();
+    let mut indexed = 0;
+    let mut i = 0;
+    while i < data.len() {
+        // copy address is 16 bits
+        while indexed < i.min(0x10000) {
+            if indexed + 1 < data.len() {
+                let key = [data[indexed], data[indexed + 1]];
+                prefixes.entry(key).or_default().push(indexed);
+            }
+            indexed += 1;
+        }
+        let max = (data.len() - i).min(LZ_MAX_LEN);
+        let run = |f: &dyn Fn(usize) -> u8| (0..max).take_while(|j| data[i + j] == f(*j)).count();
+
+        let byte_fill = run(&|_| data[i]);
+        let word_fill = if max >= 2 {
+            run(&|j| data[i + j % 2])
+        } else {
+            0
+        };
+        let inc_fill = run(&|j| data[i].wrapping_add(j as u8));
+        // check the most recent candidates only
+        let candidates = match data.get(i..i + 2) {
+            Some(key) => prefixes.get(key).map_or(&[][..], |v| v.as_slice()),
+            None => &[],
+        };
+        let (repeat, addr) = candidates
+            .iter()
+            .rev()
+            .take(256)
+            .map(|&a| {
+                let len = (0..max).take_while(|j| data[a + j] == data[i + j]).count();
+                (len, a)
+            })
+            .max_by_key(|(len, _)| *len)
+            .unwrap_or((0, 0));
+
+        // (command, length, bytes after the header)
+        let best = [
+            (LZ_BYTE_FILL, byte_fill, 1),
+            (LZ_WORD_FILL, word_fill, 2),
+            (LZ_INC_FILL, inc_fill, 1),
+            (LZ_REPEAT, repeat, 2),
+        ]
+        .into_iter()
+        .map(|(cmd, len, arg)| {
+            let cost = arg + if len > 32 { 2 } else { 1 };
+            (cmd, len, len as isize - cost as isize)
+        })
+        .max_by_key(|(_, _, saved)| *saved);
+
+        match best {
+            Some((cmd, len, saved)) if saved > 1 => {
+                lz_flush_copy(&mut out, &mut literal);
+                lz_header(&mut out, cmd, len);
+                match cmd {
+                    LZ_WORD_FILL => out.extend(&data[i..i + 2]),
+                    LZ_REPEAT => out.extend(if big_endian {
+                        (addr as u16).to_be_bytes()
+                    } else {
+                        (addr as u16).to_le_bytes()
+                    }),
+                    _ => out.push(data[i]),
+                }
+                i += len;
+            }
+            _ => {
+                literal.push(data[i]);
+                i += 1;
+            }
+        }
+    }
+    lz_flush_copy(&mut out, &mut literal);
+    out.push(LZ_END);
+    out
+}
+
+// header byte n
+// 0x00-0x7F: copy the next n+1 bytes
+// 0x80-0xFE: repeat the next byte (n & 0x7F)+1 times
+// 0xFF: end of data
+const RLE_END: u8 = 0xFF;
+const RLE_MAX_COPY: usize = 0x80;
+const RLE_MAX_RUN: usize = 0x7F;
+
+fn rle_decompress(content: &[u8]) -> std::io::Result<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    loop {
+        let header = *content.get(i).ok_or_else(truncated)?;
+        i += 1;
+        if header == RLE_END {
+            return Ok((out, i));
+        }
+        if header < 0x80 {
+            let len = header as usize + 1;
+            out.extend(content.get(i..i + len).ok_or_else(truncated)?);
+            i += len;
+        } else {
+            let b = *content.get(i).ok_or_else(truncated)?;
+            out.extend(std::iter::repeat_n(b, (header & 0x7F) as usize + 1));
+            i += 1;
+        }
+    }
+}
+
+fn rle_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut literal: Vec<u8> = Vec::new();
+    let flush = |out: &mut Vec<u8>, literal: &mut Vec<u8>| {
+        for chunk in literal.chunks(RLE_MAX_COPY) {
+            out.push(chunk.len() as u8 - 1);
+            out.extend(chunk);
+        }
+        literal.clear();
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(RLE_MAX_RUN)
+            .take_while(|b| **b == data[i])
+            .count();
+        // a run of 3 or more is never longer than copying
+        if run >= 3 {
+            flush(&mut out, &mut literal);
+            out.push(0x80 | (run - 1) as u8);
+            out.push(data[i]);
+            i += run;
+        } else {
+            literal.push(data[i]);
+            i += 1;
+        }
+    }
+    flush(&mut out, &mut literal);
+    out.push(RLE_END);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODECS: [Compression; 4] = [
+        Compression::None,
+        Compression::LZ1,
+        Compression::LZ2,
+        Compression::RLE,
+    ];
+
+    // xorshift, so the data doesn't compress
+    fn noise(len: usize, mut seed: u32) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect()
+    }
+
+    fn round_trip(compression: Compression, data: &[u8]) -> Vec<u8> {
+        let content = compression.compress(data);
+        let (out, len) = compression.decompress_len(&content).unwrap();
+        assert_eq!(out, data, "{compression:?} round trip");
+        assert_eq!(len, content.len(), "{compression:?} bytes read");
+        content
+    }
+
+    #[test]
+    fn empty() {
+        for compression in CODECS {
+            round_trip(compression, &[]);
+        }
+        assert_eq!(Compression::LZ2.compress(&[]), [LZ_END]);
+        assert_eq!(Compression::RLE.compress(&[]), [RLE_END]);
+        // no end marker
+        assert!(Compression::LZ2.decompress(&[]).is_err());
+        assert!(Compression::RLE.decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn mixed_data() {
+        let mut data = noise(300, 1);
+        data.extend([0x12; 40]);
+        data.extend((0..50).map(|i| [0x34, 0x56][i % 2]));
+        data.extend(0..=255);
+        data.extend_from_within(10..100);
+        for compression in CODECS {
+            round_trip(compression, &data);
+        }
+    }
+
+    #[test]
+    fn long_runs() {
+        // 1024 + 1024 + 952, 3 bytes each
+        let data = vec![7; 3000];
+        assert_eq!(round_trip(Compression::LZ1, &data).len(), 10);
+        assert_eq!(round_trip(Compression::LZ2, &data).len(), 10);
+        // 127 + 127 + 46, 2 bytes each
+        assert_eq!(round_trip(Compression::RLE, &data[..300]).len(), 7);
+
+        // literals longer than one copy command
+        let data = noise(3000, 2);
+        for compression in CODECS {
+            round_trip(compression, &data);
+        }
+    }
+
+    #[test]
+    fn repeat_at_window_limit() {
+        // last repeatable address is 0xFFFF, data past it is only copied
+        let pattern = noise(64, 3);
+        let mut data = noise(0xFFF0, 4);
+        data.extend(&pattern);
+        data.extend(noise(64, 5));
+        data.extend(&pattern);
+        data.extend_from_within(0x10010..0x10050);
+
+        // long repeat of 64 bytes from 0xFFF0
+        let lz1 = round_trip(Compression::LZ1, &data);
+        assert!(lz1.windows(4).any(|w| w == [0xF0, 0x3F, 0xF0, 0xFF]));
+        let lz2 = round_trip(Compression::LZ2, &data);
+        assert!(lz2.windows(4).any(|w| w == [0xF0, 0x3F, 0xFF, 0xF0]));
+    }
+
+    #[test]
+    fn decode_lz2() {
+        let content = [
+            0x22, 0xAA, // byte fill x3
+            0x41, 0x12, 0x34, // word fill x2
+            0x63, 0x05, // increasing fill x4
+            0x01, 0x10, 0x20, // copy 2 bytes
+            0x82, 0x00, 0x03, // repeat 3 bytes from 0x0003
+            0xE4, 0x27, 0x55, // long byte fill x40
+            0xFF, 0x99, // end, trailing byte is ignored
+        ];
+        let mut expected = vec![
+            0xAA, 0xAA, 0xAA, 0x12, 0x34, 0x05, 0x06, 0x07, 0x08, 0x10, 0x20, 0x12, 0x34, 0x05,
+        ];
+        expected.extend([0x55; 40]);
+        assert_eq!(
+            Compression::LZ2.decompress_len(&content).unwrap(),
+            (expected, content.len() - 1)
+        );
+        // LZ1 reads the address as 0x0300
+        assert!(Compression::LZ1.decompress(&content).is_err());
+    }
+}
@@ -1,8 +1,35 @@
-use strum::EnumString;
+use std::fmt;
 
-#[derive(EnumString, Debug, Copy, Clone, Default, Eq, PartialEq)]
+use gtk::glib;
+use strum::{EnumIter, EnumString};
+
+#[derive(EnumString, EnumIter, Debug, Copy, Clone, Default, Eq, PartialEq, glib::Enum)]
+#[enum_type(name = "PaletteFile")]
 pub enum PaletteFile {
     #[default]
+    #[enum_value(nick = "bgr555")]
     BGR555,
+    #[enum_value(nick = "rgb24")]
     RGB24,
+    #[enum_value(nick = "jasc")]
+    JASC,
+    #[enum_value(nick = "gpl")]
+    GPL,
+    #[enum_value(nick = "act")]
+    ACT,
+    #[enum_value(nick = "hex")]
+    Hex,
+}
+
+impl fmt::Display for PaletteFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteFile::BGR555 => write!(f, "bgr555"),
+            PaletteFile::RGB24 => write!(f, "rgb24"),
+            PaletteFile::JASC => write!(f, "jasc"),
+            PaletteFile::GPL => write!(f, "gpl"),
+            PaletteFile::ACT => write!(f, "act"),
+            PaletteFile::Hex => write!(f, "hex"),
+        }
+    }
 }
@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+
+use super::color::Color;
+use super::list_items::{Bpp, TilemapSize};
+use super::palette::Palette;
+use super::tilemap::{Tile, Tilemap};
+use super::tiles::{TileData, Tileset};
+
+// decoded image, 1 rgba value per pixel
+pub struct Rgba {
+    pub pixels: Vec<[u8; 4]>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rgba {
+    pub fn from_png(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_png_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_png_bytes(content: &[u8]) -> std::io::Result<Self> {
+        Self::from_png_reader(content)
+    }
+
+    pub fn from_png_reader(reader: impl Read) -> std::io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        // expand indexed and low bit depth images, strip 16 bit channels
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let buf = &buf[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            png::ColorType::Grayscale => buf.iter().map(|&v| [v, v, v, 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::Rgba => buf
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            png::ColorType::Indexed => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "indexed image was not expanded",
+                ))
+            }
+        };
+        Ok(Self {
+            pixels,
+            width: info.width as usize,
+            height: info.height as usize,
+        })
+    }
+
+    pub fn to_png_bytes(&self) -> std::io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.write_png(&mut content)?;
+        Ok(content)
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> std::io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels.concat())?;
+        Ok(())
+    }
+
+    // None for transparent pixels
+    fn color_at(&self, x: usize, y: usize) -> Option<Color> {
+        let [r, g, b, a] = self.pixels[y * self.width + x];
+        if a < 128 {
+            return None;
+        }
+        Some(
+            Color::new()
+                .with_red(r >> 3)
+                .with_green(g >> 3)
+                .with_blue(b >> 3),
+        )
+    }
+}
+
+pub fn import_png(
+    path: &std::path::PathBuf,
+    bpp: Bpp,
+    palette_base: usize,
+    palette: Palette,
+) -> std::io::Result<(Tileset, Tilemap, Palette)> {
+    import_image(&Rgba::from_png(path)?, bpp, palette_base, palette)
+}
+
+// cut an image into 8x8 tiles, merging duplicate (and flipped) tiles
+// subpalettes are written to palette starting at palette_base, color 0 of each is left as is
+pub fn import_image(
+    image: &Rgba,
+    bpp: Bpp,
+    palette_base: usize,
+    mut palette: Palette,
+) -> std::io::Result<(Tileset, Tilemap, Palette)> {
+    if !image.width.is_multiple_of(8) || !image.height.is_multiple_of(8) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "image size is {}x{} but should be a multiple of 8",
+                image.width, image.height
+            ),
+        ));
+    }
+    let (tiles_w, tiles_h) = (image.width / 8, image.height / 8);
+    let size = [
+        TilemapSize::SC32x32,
+        TilemapSize::SC64x32,
+        TilemapSize::SC32x64,
+        TilemapSize::SC64x64,
+    ]
+    .into_iter()
+    .find(|s| tiles_w <= s.dims().0 && tiles_h <= s.dims().1)
+    .ok_or(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "image must not exceed 512x512 pixels",
+    ))?;
+
+    // color 0 of every subpalette is transparent
+    let max_colors = bpp.to_val() as usize - 1;
+    let max_subpalettes = match bpp {
+        Bpp::Eight => 1,
+        Bpp::Two | Bpp::Four => 8,
+    };
+
+    // assign each tile to a subpalette
+    let mut subpalettes: Vec<Vec<Color>> = Vec::new();
+    let mut tile_palettes = Vec::new();
+    for ty in 0..tiles_h {
+        for tx in 0..tiles_w {
+            let mut colors = Vec::new();
+            for i in 0..64 {
+                if let Some(c) = image.color_at(tx * 8 + i % 8, ty * 8 + i / 8) {
+                    if !colors.contains(&c) {
+                        colors.push(c);
+                    }
+                }
+            }
+            if colors.len() > max_colors {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "tile at ({tx}, {ty}) uses {} colors but a {bpp} subpalette holds {max_colors}",
+                        colors.len()
+                    ),
+                ));
+            }
+
+            // reuse the subpalette sharing the most colors, or start a new one
+            let best = subpalettes
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let shared = colors.iter().filter(|c| p.contains(c)).count();
+                    (i, shared, p.len() + colors.len() - shared)
+                })
+                .filter(|(_, _, total)| *total <= max_colors)
+                .max_by_key(|(_, shared, _)| *shared);
+            let p = match best {
+                Some((i, _, _)) => i,
+                None if subpalettes.len() < max_subpalettes => {
+                    subpalettes.push(Vec::new());
+                    subpalettes.len() - 1
+                }
+                None => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("image needs more than {max_subpalettes} subpalettes"),
+                    ))
+                }
+            };
+            for c in colors {
+                if !subpalettes[p].contains(&c) {
+                    subpalettes[p].push(c);
+                }
+            }
+            tile_palettes.push(p);
+        }
+    }
+
+    // convert tiles to color indices and remove duplicates
+    let (width, height) = size.dims();
+    let mut tilemap = Tilemap::new(width, height);
+    let mut tileset = Tileset(Vec::new());
+    let mut seen: HashMap<[u8; 64], usize> = HashMap::new();
+    for (i, p) in tile_palettes.into_iter().enumerate() {
+        let (tx, ty) = (i % tiles_w, i / tiles_w);
+        let mut chr = [0; 64];
+        for (j, v) in chr.iter_mut().enumerate() {
+            if let Some(c) = image.color_at(tx * 8 + j % 8, ty * 8 + j / 8) {
+                *v = subpalettes[p].iter().position(|x| *x == c).unwrap() as u8 + 1;
+            }
+        }
+        let data = TileData(chr);
+
+        let found = [(false, false), (true, false), (false, true), (true, true)]
+            .into_iter()
+            .find_map(|(x_flip, y_flip)| {
+                seen.get(&data.flipped(x_flip, y_flip).0)
+                    .map(|idx| (*idx, x_flip, y_flip))
+            });
+        let (idx, x_flip, y_flip) = match found {
+            Some(res) => res,
+            None => {
+                if tileset.0.len() >= Tileset::MAX {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("tile count exceeds maximum of {} tiles", Tileset::MAX),
+                    ));
+                }
+                seen.insert(data.0, tileset.0.len());
+                tileset.0.push(data);
+                (tileset.0.len() - 1, false, false)
+            }
+        };
+
+        tilemap.tiles[ty * width + tx] = Tile::new()
+            .with_tile_idx(idx as u16)
+            .with_palette(p as u8)
+            .with_x_flip(x_flip)
+            .with_y_flip(y_flip);
+    }
+
+    // write subpalettes, skipping color 0
+    for (p, colors) in subpalettes.into_iter().enumerate() {
+        let start = palette_base + p * bpp.to_val() as usize;
+        for (i, c) in colors.into_iter().enumerate() {
+            palette.0[start + 1 + i] = c;
+        }
+    }
+
+    Ok((tileset, tilemap, palette))
+}
+
+// tileset sheet keeps the tile count, the last row may be padded with blank tiles
+const SHEET_TILE_COUNT: &str = "TileCount";
+
+// write tileset as an indexed image, 16 tiles per row
+// colors are the subpalette used for the PLTE chunk
+pub fn write_tileset_png(
+    tileset: &Tileset,
+    colors: &[Color],
+    bpp: Bpp,
+    writer: impl Write,
+) -> std::io::Result<()> {
+    let rows = tileset.0.len().div_ceil(16).max(1);
+    let (width, height) = (16 * 8, rows * 8);
+
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(match bpp {
+        Bpp::Two => png::BitDepth::Two,
+        Bpp::Four => png::BitDepth::Four,
+        Bpp::Eight => png::BitDepth::Eight,
+    });
+    encoder.set_palette(
+        colors
+            .iter()
+            .flat_map(|c| {
+                let (r, g, b) = c.to_rgb24();
+                [r, g, b]
+            })
+            .collect::<Vec<_>>(),
+    );
+    encoder.add_text_chunk(SHEET_TILE_COUNT.to_string(), tileset.0.len().to_string())?;
+    let mut writer = encoder.write_header()?;
+
+    // pack pixels of each row, leftmost pixel in the highest bits
+    let bits = bpp.bits() as usize;
+    let mut data = Vec::new();
+    for y in 0..height {
+        let mut row = vec![0; width * bits / 8];
+        for x in 0..width {
+            let idx = (y / 8) * 16 + x / 8;
+            let c = tileset.0.get(idx).map_or(0, |t| t.0[(y % 8) * 8 + x % 8]);
+            let shift = 8 - bits - (x * bits) % 8;
+            row[x * bits / 8] |= c << shift;
+        }
+        data.extend(row);
+    }
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+// read an indexed image back into tiles, pixel values are used as is
+pub fn read_tileset_png(path: &std::path::PathBuf, bpp: Bpp) -> std::io::Result<Tileset> {
+    read_tileset_png_from(BufReader::new(File::open(path)?), bpp)
+}
+
+pub fn read_tileset_png_from(reader: impl Read, bpp: Bpp) -> std::io::Result<Tileset> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    if info.color_type != png::ColorType::Indexed {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "image is not indexed",
+        ));
+    }
+    let (width, height) = (info.width as usize, info.height as usize);
+    if width % 8 != 0 || height % 8 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("image size is {width}x{height} but should be a multiple of 8"),
+        ));
+    }
+
+    // unpack pixels, leftmost pixel in the highest bits
+    let bits = info.bit_depth as usize;
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buf[..info.buffer_size()].chunks_exact(info.line_size) {
+        for x in 0..width {
+            let shift = 8 - bits - (x * bits) % 8;
+            pixels.push((row[x * bits / 8] >> shift) & ((1 << bits) - 1) as u8);
+        }
+    }
+    if let Some(v) = pixels.iter().find(|v| **v as u16 >= bpp.to_val()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("pixel value {v} does not fit in {bpp}"),
+        ));
+    }
+
+    // cut into tiles, left to right then top to bottom
+    let tiles_w = width / 8;
+    let mut tiles: Vec<TileData> = (0..tiles_w * height / 8)
+        .map(|i| {
+            let (tx, ty) = (i % tiles_w, i / tiles_w);
+            let mut chr = [0; 64];
+            for (j, c) in chr.iter_mut().enumerate() {
+                *c = pixels[(ty * 8 + j / 8) * width + tx * 8 + j % 8];
+            }
+            TileData(chr)
+        })
+        .collect();
+
+    // drop padding tiles of a sheet written by write_tileset_png
+    let count = reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .find(|t| t.keyword == SHEET_TILE_COUNT)
+        .and_then(|t| t.text.parse::<usize>().ok());
+    if let Some(count) = count {
+        tiles.truncate(count.max(1));
+    }
+
+    if tiles.len() > Tileset::MAX {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("tile count exceeds maximum of {} tiles", Tileset::MAX),
+        ));
+    }
+    Ok(Tileset(tiles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORS: [[u8; 4]; 4] = [
+        [248, 0, 0, 255],
+        [0, 248, 0, 255],
+        [0, 0, 248, 255],
+        [248, 248, 248, 255],
+    ];
+
+    // 8x8 tile with a different color in its top left corner, so flips can be told apart
+    fn tile(corner: [u8; 4], rest: [u8; 4]) -> Vec<[u8; 4]> {
+        (0..64)
+            .map(|i| if i == 0 { corner } else { rest })
+            .collect()
+    }
+
+    // tiles side by side in a single row, round-tripped through png
+    fn image(tiles: &[Vec<[u8; 4]>]) -> Rgba {
+        let width = tiles.len() * 8;
+        let pixels = (0..64 * tiles.len())
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                tiles[x / 8][(y % 8) * 8 + x % 8]
+            })
+            .collect();
+        let image = Rgba {
+            pixels,
+            width,
+            height: 8,
+        };
+        Rgba::from_png_bytes(&image.to_png_bytes().unwrap()).unwrap()
+    }
+
+    fn import(image: &Rgba, bpp: Bpp) -> std::io::Result<(Tileset, Tilemap, Palette)> {
+        import_image(image, bpp, 0, Palette::default())
+    }
+
+    // every pixel drawn from tileset, tilemap and palette matches the image
+    fn assert_round_trip(image: &Rgba, bpp: Bpp) {
+        let (tileset, tilemap, palette) = import(image, bpp).unwrap();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let tile = tilemap.tiles[(y / 8) * tilemap.width + x / 8];
+                let chr = tileset.0[tile.tile_idx() as usize].flipped(tile.x_flip(), tile.y_flip());
+                let v = chr.0[(y % 8) * 8 + x % 8] as usize;
+                let start = tile.palette() as usize * bpp.to_val() as usize;
+                assert_eq!(Some(palette.0[start + v]), image.color_at(x, y));
+            }
+        }
+    }
+
+    fn flips(tilemap: &Tilemap, count: usize) -> Vec<(u16, bool, bool)> {
+        tilemap.tiles[..count]
+            .iter()
+            .map(|t| (t.tile_idx(), t.x_flip(), t.y_flip()))
+            .collect()
+    }
+
+    #[test]
+    fn identical_tiles() {
+        let image = image(&[tile(COLORS[0], COLORS[1]), tile(COLORS[0], COLORS[1])]);
+        let (tileset, tilemap, _) = import(&image, Bpp::Four).unwrap();
+        assert_eq!(tileset.0.len(), 1);
+        assert_eq!(flips(&tilemap, 2), [(0, false, false); 2]);
+        assert_round_trip(&image, Bpp::Four);
+    }
+
+    #[test]
+    fn flipped_tiles() {
+        let chr = tile(COLORS[0], COLORS[1]);
+        let flipped = |x_flip, y_flip| {
+            let data = TileData(std::array::from_fn(|i| i as u8));
+            data.flipped(x_flip, y_flip)
+                .0
+                .iter()
+                .map(|i| chr[*i as usize])
+                .collect()
+        };
+        let image = image(&[
+            chr.clone(),
+            flipped(true, false),
+            flipped(false, true),
+            flipped(true, true),
+        ]);
+        let (tileset, tilemap, _) = import(&image, Bpp::Four).unwrap();
+        assert_eq!(tileset.0.len(), 1);
+        assert_eq!(
+            flips(&tilemap, 4),
+            [
+                (0, false, false),
+                (0, true, false),
+                (0, false, true),
+                (0, true, true)
+            ]
+        );
+        assert_round_trip(&image, Bpp::Four);
+    }
+
+    #[test]
+    fn too_many_colors() {
+        // a 2bpp subpalette holds 3 colors besides transparent color 0
+        let mut chr = tile(COLORS[0], COLORS[1]);
+        chr[1] = COLORS[2];
+        chr[2] = COLORS[3];
+        let image = image(&[chr]);
+        let Err(e) = import(&image, Bpp::Two) else {
+            panic!("tile with 4 colors should not fit 2bpp");
+        };
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("uses 4 colors"));
+        assert_round_trip(&image, Bpp::Four);
+    }
+}
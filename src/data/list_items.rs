@@ -11,6 +11,7 @@ pub enum Bpp {
     #[default]
     Two,
     Four,
+    Eight,
 }
 
 impl fmt::Display for Bpp {
@@ -18,6 +19,7 @@ impl fmt::Display for Bpp {
         match self {
             Bpp::Two => write!(f, "2bpp"),
             Bpp::Four => write!(f, "4bpp"),
+            Bpp::Eight => write!(f, "8bpp"),
         }
     }
 }
@@ -27,18 +29,29 @@ impl Bpp {
         match self {
             Bpp::Two => 2,
             Bpp::Four => 4,
+            Bpp::Eight => 8,
         }
     }
 
-    pub fn to_val(&self) -> u8 {
+    // number of colors in a palette
+    pub fn to_val(&self) -> u16 {
         match self {
             Bpp::Two => 4,
             Bpp::Four => 16,
+            Bpp::Eight => 256,
+        }
+    }
+
+    // idx of color 0 of the first palette, only 2bpp tiles depend on the BG
+    pub fn palette_base(&self, bg_mode: BGModeTwo) -> u8 {
+        match self {
+            Bpp::Two => bg_mode.palette_offset(),
+            Bpp::Four | Bpp::Eight => 0,
         }
     }
 }
 
-#[derive(EnumIter, Default, Debug, Copy, Clone, glib::Enum)]
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone, glib::Enum)]
 #[enum_type(name = "TileSize")]
 pub enum TileSize {
     #[default]
@@ -55,7 +68,46 @@ impl fmt::Display for TileSize {
     }
 }
 
-#[derive(EnumIter, Default, Debug, Copy, Clone, glib::Enum)]
+// tilemap size set by the BGnSC register
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TilemapSize {
+    #[default]
+    SC32x32,
+    SC64x32,
+    SC32x64,
+    SC64x64,
+}
+
+impl fmt::Display for TilemapSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (w, h) = self.dims();
+        write!(f, "{w}x{h}")
+    }
+}
+
+impl TilemapSize {
+    // width and height in tiles
+    pub fn dims(&self) -> (usize, usize) {
+        match self {
+            TilemapSize::SC32x32 => (32, 32),
+            TilemapSize::SC64x32 => (64, 32),
+            TilemapSize::SC32x64 => (32, 64),
+            TilemapSize::SC64x64 => (64, 64),
+        }
+    }
+
+    pub fn from_dims(width: usize, height: usize) -> Option<Self> {
+        match (width, height) {
+            (32, 32) => Some(TilemapSize::SC32x32),
+            (64, 32) => Some(TilemapSize::SC64x32),
+            (32, 64) => Some(TilemapSize::SC32x64),
+            (64, 64) => Some(TilemapSize::SC64x64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone, glib::Enum)]
 #[enum_type(name = "Zoom")]
 pub enum Zoom {
     Half,
@@ -84,7 +136,26 @@ impl Zoom {
     }
 }
 
-#[derive(EnumIter, Default, Debug, Copy, Clone, glib::Enum)]
+// cells the fill tool treats the same as the clicked cell
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone, glib::Enum)]
+#[enum_type(name = "FillMatch")]
+pub enum FillMatch {
+    #[default]
+    TileIdx,
+    // palette, priority and flips too
+    Tile,
+}
+
+impl fmt::Display for FillMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FillMatch::TileIdx => write!(f, "Same tile index"),
+            FillMatch::Tile => write!(f, "Same tile"),
+        }
+    }
+}
+
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone, glib::Enum)]
 #[enum_type(name = "BGModeTwo")]
 pub enum BGModeTwo {
     #[default]
@@ -121,15 +192,105 @@ pub enum DrawMode {
     #[default]
     None,
     Pen(HashSet<(usize, usize)>),
+    // region found when the fill tool is pressed
+    Fill(HashSet<(usize, usize)>),
     RectFill {
         start: (usize, usize),
         end: (usize, usize),
     },
+    Line {
+        start: (usize, usize),
+        end: (usize, usize),
+    },
+    // border of the rectangle
+    Rect {
+        start: (usize, usize),
+        end: (usize, usize),
+    },
+    // dragging out a new selection
+    Select {
+        start: (usize, usize),
+        end: (usize, usize),
+    },
+    // dragging the selection by end - start
+    Move {
+        start: (usize, usize),
+        end: (usize, usize),
+    },
 }
 
 impl DrawMode {
+    // cells on a line from start to end (Bresenham's line algorithm)
+    pub fn line_points(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+        let (mut x, mut y) = (start.0 as isize, start.1 as isize);
+        let (x1, y1) = (end.0 as isize, end.1 as isize);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        let mut points = Vec::new();
+        loop {
+            points.push((x as usize, y as usize));
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+        points
+    }
+
+    // cells on the border of the rectangle with corners start and end
+    pub fn rect_points(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+        let ((x_min, x_max), (y_min, y_max)) = (
+            (start.0.min(end.0), start.0.max(end.0)),
+            (start.1.min(end.1), start.1.max(end.1)),
+        );
+        let mut points = Vec::new();
+        for y in y_min..=y_max {
+            if y == y_min || y == y_max {
+                points.extend((x_min..=x_max).map(|x| (x, y)));
+            } else {
+                points.push((x_min, y));
+                if x_max != x_min {
+                    points.push((x_max, y));
+                }
+            }
+        }
+        points
+    }
+
+    // cells a finished stroke draws to
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        match self {
+            DrawMode::Pen(set) | DrawMode::Fill(set) => set.iter().copied().collect(),
+            DrawMode::Line { start, end } => Self::line_points(*start, *end),
+            DrawMode::Rect { start, end } => Self::rect_points(*start, *end),
+            DrawMode::RectFill { start, end } => {
+                let ((x_min, x_max), (y_min, y_max)) = (
+                    (start.0.min(end.0), start.0.max(end.0)),
+                    (start.1.min(end.1), start.1.max(end.1)),
+                );
+                (y_min..=y_max)
+                    .flat_map(|y| (x_min..=x_max).map(move |x| (x, y)))
+                    .collect()
+            }
+            DrawMode::Select { .. } | DrawMode::Move { .. } | DrawMode::None => Vec::new(),
+        }
+    }
+
     pub fn idx_in_range(&self, ix: usize, iy: usize) -> bool {
         match self {
+            DrawMode::Line { start, end } => Self::line_points(*start, *end).contains(&(ix, iy)),
             DrawMode::RectFill { start, end } => {
                 let ((x_min, x_max), (y_min, y_max)) = (
                     (start.0.min(end.0), start.0.max(end.0)),
@@ -137,8 +298,16 @@ impl DrawMode {
                 );
                 ix >= x_min && ix <= x_max && iy >= y_min && iy <= y_max
             }
-            DrawMode::Pen(set) => set.contains(&(ix, iy)),
-            DrawMode::None => false,
+            DrawMode::Rect { start, end } => {
+                let ((x_min, x_max), (y_min, y_max)) = (
+                    (start.0.min(end.0), start.0.max(end.0)),
+                    (start.1.min(end.1), start.1.max(end.1)),
+                );
+                let on_edge = ix == x_min || ix == x_max || iy == y_min || iy == y_max;
+                on_edge && ix >= x_min && ix <= x_max && iy >= y_min && iy <= y_max
+            }
+            DrawMode::Pen(set) | DrawMode::Fill(set) => set.contains(&(ix, iy)),
+            DrawMode::Select { .. } | DrawMode::Move { .. } | DrawMode::None => false,
         }
     }
 }
@@ -1,6 +1,14 @@
 pub mod color;
+pub mod compression;
 pub mod file_format;
+pub mod image;
 pub mod list_items;
+pub mod mode7;
 pub mod palette;
+pub mod project;
+pub mod render;
+pub mod rom;
+pub mod source;
 pub mod tilemap;
 pub mod tiles;
+pub mod vram;
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
+use itertools::Itertools;
+
+use super::{tilemap::Tilemap, tiles::Tileset};
+
+// size of the interleaved mode 7 VRAM data
+pub const VRAM_SIZE: usize = 0x8000;
+
+// low byte of each VRAM word stores the tilemap, high byte stores the character data
+pub fn from_file(path: &std::path::PathBuf) -> std::io::Result<(Tilemap, Tileset)> {
+    from_bytes(&std::fs::read(path)?)
+}
+
+pub fn from_reader(mut reader: impl Read) -> std::io::Result<(Tilemap, Tileset)> {
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content)?;
+    from_bytes(&content)
+}
+
+pub fn from_bytes(content: &[u8]) -> std::io::Result<(Tilemap, Tileset)> {
+    if content.len() != VRAM_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("file must be {VRAM_SIZE} bytes"),
+        ));
+    }
+
+    let (map, chr): (Vec<u8>, Vec<u8>) = content.iter().copied().tuples().unzip();
+    Ok((
+        Tilemap::from_bytes_mode7(&map)?,
+        Tileset::from_bytes_mode7(&chr)?,
+    ))
+}
+
+pub fn to_bytes(tilemap: &Tilemap, tileset: &Tileset) -> std::io::Result<Vec<u8>> {
+    let mut map = tilemap.to_bytes_mode7()?;
+    let mut chr = tileset.to_bytes_mode7();
+    // pad to full size with 0
+    map.resize(VRAM_SIZE / 2, 0);
+    chr.resize(VRAM_SIZE / 2, 0);
+
+    Ok(map.into_iter().zip(chr).flat_map(|(m, c)| [m, c]).collect())
+}
+
+pub fn to_writer(
+    tilemap: &Tilemap,
+    tileset: &Tileset,
+    mut writer: impl Write,
+) -> std::io::Result<()> {
+    writer.write_all(&to_bytes(tilemap, tileset)?)
+}
+
+pub fn write_to_file(tilemap: &Tilemap, tileset: &Tileset, f: &File) -> std::io::Result<()> {
+    to_writer(tilemap, tileset, f)
+}
@@ -1,10 +1,12 @@
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::mem::{self, MaybeUninit};
 
 use itertools::Itertools;
 
 use super::color::Color;
+use super::compression::Compression;
+use super::file_format::PaletteFile;
 
 pub struct Palette(pub [Color; 256]);
 
@@ -54,7 +56,11 @@ impl Default for Palette {
 
 impl Palette {
     pub fn from_file_bgr555(path: &std::path::PathBuf) -> std::io::Result<Self> {
-        let mut content = std::fs::read(&path)?;
+        Ok(Self::from_bytes_bgr555(&std::fs::read(path)?))
+    }
+
+    pub fn from_bytes_bgr555(content: &[u8]) -> Self {
+        let mut content = content.to_vec();
         let len = content.len();
         if len < 512 {
             eprintln!("file size less than 512B, pad with 0");
@@ -65,7 +71,7 @@ impl Palette {
 
         // unsafe initializing array
         // https://doc.rust-lang.org/core/mem/union.MaybeUninit.html#initializing-an-array-element-by-element
-        Ok(Self({
+        Self({
             let mut data: [MaybeUninit<Color>; 256] =
                 unsafe { MaybeUninit::uninit().assume_init() };
 
@@ -74,18 +80,113 @@ impl Palette {
             }
 
             unsafe { mem::transmute::<_, [Color; 256]>(data) }
-        }))
+        })
     }
 
-    pub fn write_file_bgr555(&self, mut file: &File) -> std::io::Result<()> {
-        for c in &self.0 {
-            file.write_all(&c.into_bytes())?;
+    pub fn to_bytes_bgr555(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|c| c.into_bytes()).collect()
+    }
+
+    pub fn to_writer_bgr555(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes_bgr555())
+    }
+
+    pub fn write_file_bgr555(&self, file: &File) -> std::io::Result<()> {
+        self.to_writer_bgr555(file)
+    }
+
+    pub fn from_bytes(content: &[u8], format: PaletteFile) -> std::io::Result<Self> {
+        match format {
+            PaletteFile::BGR555 => Ok(Self::from_bytes_bgr555(content)),
+            PaletteFile::RGB24 => Self::from_bytes_rgb24(content),
+            PaletteFile::JASC => Self::from_bytes_jasc(content),
+            PaletteFile::GPL => Self::from_bytes_gpl(content),
+            PaletteFile::ACT => Self::from_bytes_act(content),
+            PaletteFile::Hex => Self::from_bytes_hex(content),
+        }
+    }
+
+    pub fn from_reader(mut reader: impl Read, format: PaletteFile) -> std::io::Result<Self> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Self::from_bytes(&content, format)
+    }
+
+    pub fn to_bytes(&self, format: PaletteFile) -> Vec<u8> {
+        let mut content = Vec::new();
+        // writing to a Vec can't fail
+        let _ = self.to_writer(&mut content, format);
+        content
+    }
+
+    pub fn to_writer(&self, writer: impl Write, format: PaletteFile) -> std::io::Result<()> {
+        match format {
+            PaletteFile::BGR555 => self.to_writer_bgr555(writer),
+            PaletteFile::RGB24 => self.to_writer_rgb24(writer),
+            PaletteFile::JASC => self.to_writer_jasc(writer),
+            PaletteFile::GPL => self.to_writer_gpl(writer),
+            PaletteFile::ACT => self.to_writer_act(writer),
+            PaletteFile::Hex => self.to_writer_hex(writer),
+        }
+    }
+
+    // compression only applies to BGR555, the other formats are never compressed
+    pub fn from_file_format(
+        path: &std::path::PathBuf,
+        format: PaletteFile,
+        compression: Compression,
+    ) -> std::io::Result<Self> {
+        let content = match format {
+            PaletteFile::BGR555 => compression.read_file(path)?,
+            _ => std::fs::read(path)?,
+        };
+        Self::from_bytes(&content, format)
+    }
+
+    pub fn write_file_format(
+        &self,
+        file: &File,
+        format: PaletteFile,
+        compression: Compression,
+    ) -> std::io::Result<()> {
+        match format {
+            PaletteFile::BGR555 => compression.write_file(file, &self.to_bytes_bgr555()),
+            _ => self.to_writer(file, format),
+        }
+    }
+
+    // any number of colors, for loading into part of a palette
+    pub fn read_colors_bgr555(path: &std::path::PathBuf) -> std::io::Result<Vec<Color>> {
+        Self::colors_from_bytes_bgr555(&std::fs::read(path)?)
+    }
+
+    pub fn colors_from_bytes_bgr555(content: &[u8]) -> std::io::Result<Vec<Color>> {
+        let len = content.len();
+        if len == 0 || !len.is_multiple_of(2) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("file size is {len} but should be a non-zero multiple of 2"),
+            ));
+        }
+        Ok(content
+            .iter()
+            .tuples()
+            .map(|(&lo, &hi)| Color::from_bytes([lo, hi]))
+            .collect())
+    }
+
+    pub fn write_colors_bgr555(colors: &[Color], mut writer: impl Write) -> std::io::Result<()> {
+        for c in colors {
+            writer.write_all(&c.into_bytes())?;
         }
         Ok(())
     }
 
     pub fn from_file_rgb24(path: &std::path::PathBuf) -> std::io::Result<Self> {
-        let content = std::fs::read(&path)?;
+        Self::from_bytes_rgb24(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes_rgb24(content: &[u8]) -> std::io::Result<Self> {
         let len = content.len();
         if len != 3 * 256 {
             return Err(std::io::Error::new(
@@ -100,7 +201,7 @@ impl Palette {
             let mut data: [MaybeUninit<Color>; 256] =
                 unsafe { MaybeUninit::uninit().assume_init() };
 
-            for (i, (r, g, b)) in content.into_iter().tuples().enumerate() {
+            for (i, (&r, &g, &b)) in content.iter().tuples().enumerate() {
                 let r = r >> 3;
                 let g = g >> 3;
                 let b = b >> 3;
@@ -111,13 +212,225 @@ impl Palette {
         }))
     }
 
-    pub fn write_file_rgb24(&self, mut file: &File) -> std::io::Result<()> {
+    pub fn to_writer_rgb24(&self, mut writer: impl Write) -> std::io::Result<()> {
         for c in &self.0 {
             let r = c.red() << 3 | c.red() >> 2;
             let g = c.green() << 3 | c.green() >> 2;
             let b = c.blue() << 3 | c.blue() >> 2;
-            let _ = file.write_all(&[r, g, b])?;
+            let _ = writer.write_all(&[r, g, b])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_file_rgb24(&self, file: &File) -> std::io::Result<()> {
+        self.to_writer_rgb24(file)
+    }
+}
+
+// text and adobe formats
+impl Palette {
+    // missing colors are filled with black, extra colors are dropped
+    fn from_colors(colors: Vec<Color>) -> Self {
+        let len = colors.len();
+        if len < 256 {
+            eprintln!("{len} colors found, pad with black");
+        } else if len > 256 {
+            eprintln!("{len} colors found, trim extra colors");
+        }
+        let mut data = [Color::new(); 256];
+        for (c, new_c) in data.iter_mut().zip(colors) {
+            *c = new_c;
+        }
+        Self(data)
+    }
+
+    fn text(content: &[u8]) -> std::io::Result<&str> {
+        std::str::from_utf8(content).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "file is not valid UTF-8")
+        })
+    }
+
+    fn invalid_line(n: usize, line: &str) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid color on line {}: \"{line}\"", n + 1),
+        )
+    }
+
+    // "r g b" with values 0-255, anything after the 3rd value is ignored
+    fn parse_rgb_line(n: usize, line: &str) -> std::io::Result<Color> {
+        let v: Vec<u8> = line
+            .split_whitespace()
+            .take(3)
+            .map(|x| x.parse::<u8>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| Self::invalid_line(n, line))?;
+        match v[..] {
+            [r, g, b] => Ok(Color::from_rgb24(r, g, b)),
+            _ => Err(Self::invalid_line(n, line)),
+        }
+    }
+
+    pub fn from_file_jasc(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_bytes_jasc(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes_jasc(content: &[u8]) -> std::io::Result<Self> {
+        let content = Self::text(content)?;
+        let mut lines = content.lines().enumerate();
+        // header: magic, version, color count
+        if lines.next().is_none_or(|(_, l)| l.trim() != "JASC-PAL") {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "missing JASC-PAL header",
+            ));
+        }
+        let _version = lines.next();
+        let Some(count) = lines
+            .next()
+            .and_then(|(_, l)| l.trim().parse::<usize>().ok())
+        else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "missing color count",
+            ));
+        };
+
+        let colors = lines
+            .filter(|(_, l)| !l.trim().is_empty())
+            .take(count)
+            .map(|(n, l)| Self::parse_rgb_line(n, l))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Self::from_colors(colors))
+    }
+
+    pub fn to_writer_jasc(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "JASC-PAL\r\n0100\r\n{}\r", self.0.len())?;
+        for c in &self.0 {
+            let (r, g, b) = c.to_rgb24();
+            writeln!(writer, "{r} {g} {b}\r")?;
         }
         Ok(())
     }
+
+    pub fn write_file_jasc(&self, file: &File) -> std::io::Result<()> {
+        self.to_writer_jasc(file)
+    }
+
+    pub fn from_file_gpl(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_bytes_gpl(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes_gpl(content: &[u8]) -> std::io::Result<Self> {
+        let content = Self::text(content)?;
+        let mut lines = content.lines().enumerate();
+        if lines.next().is_none_or(|(_, l)| l.trim() != "GIMP Palette") {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "missing GIMP Palette header",
+            ));
+        }
+
+        // skip name, columns, comments and empty lines
+        let colors = lines
+            .filter(|(_, l)| {
+                let l = l.trim();
+                !(l.is_empty()
+                    || l.starts_with('#')
+                    || l.starts_with("Name:")
+                    || l.starts_with("Columns:"))
+            })
+            .map(|(n, l)| Self::parse_rgb_line(n, l))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Self::from_colors(colors))
+    }
+
+    pub fn to_writer_gpl(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "GIMP Palette\nName: waffle\nColumns: 16\n#")?;
+        for (i, c) in self.0.iter().enumerate() {
+            let (r, g, b) = c.to_rgb24();
+            writeln!(writer, "{r:3} {g:3} {b:3}\tIndex {i}")?;
+        }
+        Ok(())
+    }
+
+    pub fn write_file_gpl(&self, file: &File) -> std::io::Result<()> {
+        self.to_writer_gpl(file)
+    }
+
+    pub fn from_file_act(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_bytes_act(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes_act(content: &[u8]) -> std::io::Result<Self> {
+        // 256 colors, optionally followed by color count and transparent index (u16 big endian)
+        let count = match content.len() {
+            768 => 256,
+            772 => u16::from_be_bytes([content[768], content[769]]) as usize,
+            len => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("file size is {len} but should be 768 or 772 bytes"),
+                ))
+            }
+        };
+
+        let colors = content[..768]
+            .iter()
+            .tuples()
+            .take(count.min(256))
+            .map(|(r, g, b)| Color::from_rgb24(*r, *g, *b))
+            .collect();
+        Ok(Self::from_colors(colors))
+    }
+
+    pub fn to_writer_act(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for c in &self.0 {
+            let (r, g, b) = c.to_rgb24();
+            writer.write_all(&[r, g, b])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_file_act(&self, file: &File) -> std::io::Result<()> {
+        self.to_writer_act(file)
+    }
+
+    pub fn from_file_hex(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_bytes_hex(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes_hex(content: &[u8]) -> std::io::Result<Self> {
+        let content = Self::text(content)?;
+        // one #rrggbb per line, skip empty lines and ; comments
+        let colors = content
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| {
+                let l = l.trim();
+                !(l.is_empty() || l.starts_with(';'))
+            })
+            .map(|(n, l)| {
+                let hex = l.trim().trim_start_matches('#');
+                if hex.len() != 6 {
+                    return Err(Self::invalid_line(n, l));
+                }
+                let v = u32::from_str_radix(hex, 16).map_err(|_| Self::invalid_line(n, l))?;
+                Ok(Color::from_rgb24((v >> 16) as u8, (v >> 8) as u8, v as u8))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Self::from_colors(colors))
+    }
+
+    pub fn to_writer_hex(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for c in &self.0 {
+            let (r, g, b) = c.to_rgb24();
+            writeln!(writer, "#{r:02x}{g:02x}{b:02x}")?;
+        }
+        Ok(())
+    }
+
+    pub fn write_file_hex(&self, file: &File) -> std::io::Result<()> {
+        self.to_writer_hex(file)
+    }
 }
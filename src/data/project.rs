@@ -0,0 +1,205 @@
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use gtk::glib::{KeyFile, KeyFileFlags};
+
+use super::{
+    compression::Compression,
+    file_format::PaletteFile,
+    list_items::{BGModeTwo, Bpp, TileSize, TilemapSize, Zoom},
+};
+
+// place in a rom file an asset was read from, offset doesn't count the copier header
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomPlace {
+    pub offset: usize,
+    pub len: usize,
+}
+
+// asset files and view settings, stored as a key file with paths relative to the project
+#[derive(Default, Debug, Clone)]
+pub struct Project {
+    pub palette_file: Option<PathBuf>,
+    pub palette_format: PaletteFile,
+    pub palette_compression: Compression,
+    pub palette_rom: Option<RomPlace>,
+
+    pub tileset_file: Option<PathBuf>,
+    pub tileset_compression: Compression,
+    pub tileset_rom: Option<RomPlace>,
+    pub tileset_mode7: bool,
+
+    pub tilemap_file: Option<PathBuf>,
+    pub tilemap_size: TilemapSize,
+    pub tilemap_compression: Compression,
+    pub tilemap_rom: Option<RomPlace>,
+    pub tilemap_mode7: bool,
+
+    pub tile_bpp: Bpp,
+    pub tile_size: TileSize,
+    pub bg_mode: BGModeTwo,
+    pub zoom: Zoom,
+}
+
+fn invalid_value(group: &str, key: &str, value: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("invalid value \"{value}\" for {key} in [{group}]"),
+    )
+}
+
+// enum stored by variant name, default if the key is missing
+fn get_enum<T: FromStr + Default>(kf: &KeyFile, group: &str, key: &str) -> std::io::Result<T> {
+    match kf.string(group, key) {
+        Ok(value) => T::from_str(&value).map_err(|_| invalid_value(group, key, &value)),
+        Err(_) => Ok(T::default()),
+    }
+}
+
+// path relative to the project directory, or absolute if it is on another root
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts
+        .iter()
+        .zip(&base_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+    base_parts[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_parts[common..].iter().copied())
+        .collect()
+}
+
+impl Project {
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let kf = KeyFile::new();
+        kf.load_from_file(path, KeyFileFlags::NONE)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let file = |group: &str| kf.string(group, "file").ok().map(|f| dir.join(f.as_str()));
+        let rom = |group: &str| {
+            kf.uint64(group, "rom_offset").ok().map(|offset| RomPlace {
+                offset: offset as usize,
+                len: kf.uint64(group, "rom_length").unwrap_or_default() as usize,
+            })
+        };
+        // older projects only have the view setting
+        let mode7 = kf.boolean("view", "mode7").unwrap_or_default();
+
+        Ok(Self {
+            palette_file: file("palette"),
+            palette_format: get_enum(&kf, "palette", "format")?,
+            palette_compression: get_enum(&kf, "palette", "compression")?,
+            palette_rom: rom("palette"),
+            tileset_file: file("tileset"),
+            tileset_compression: get_enum(&kf, "tileset", "compression")?,
+            tileset_rom: rom("tileset"),
+            tileset_mode7: kf.boolean("tileset", "mode7").unwrap_or(mode7),
+            tilemap_file: file("tilemap"),
+            tilemap_size: get_enum(&kf, "tilemap", "size")?,
+            tilemap_compression: get_enum(&kf, "tilemap", "compression")?,
+            tilemap_rom: rom("tilemap"),
+            tilemap_mode7: kf.boolean("tilemap", "mode7").unwrap_or(mode7),
+            tile_bpp: get_enum(&kf, "view", "tile_bpp")?,
+            tile_size: get_enum(&kf, "view", "tile_size")?,
+            bg_mode: get_enum(&kf, "view", "bg_mode")?,
+            zoom: get_enum(&kf, "view", "zoom")?,
+        })
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
+        let kf = KeyFile::new();
+        let dir = std::path::absolute(path)?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let set_file = |group: &str, file: &Option<PathBuf>| {
+            if let Some(file) = file {
+                let file = relative_path(&std::path::absolute(file).unwrap_or(file.clone()), &dir);
+                kf.set_string(group, "file", &file.to_string_lossy());
+            }
+        };
+        let set_rom = |group: &str, rom: &Option<RomPlace>| {
+            if let Some(rom) = rom {
+                kf.set_uint64(group, "rom_offset", rom.offset as u64);
+                kf.set_uint64(group, "rom_length", rom.len as u64);
+            }
+        };
+
+        set_file("palette", &self.palette_file);
+        kf.set_string("palette", "format", &format!("{:?}", self.palette_format));
+        kf.set_string(
+            "palette",
+            "compression",
+            &format!("{:?}", self.palette_compression),
+        );
+        set_rom("palette", &self.palette_rom);
+        set_file("tileset", &self.tileset_file);
+        kf.set_string(
+            "tileset",
+            "compression",
+            &format!("{:?}", self.tileset_compression),
+        );
+        set_rom("tileset", &self.tileset_rom);
+        kf.set_boolean("tileset", "mode7", self.tileset_mode7);
+        set_file("tilemap", &self.tilemap_file);
+        kf.set_string("tilemap", "size", &format!("{:?}", self.tilemap_size));
+        kf.set_string(
+            "tilemap",
+            "compression",
+            &format!("{:?}", self.tilemap_compression),
+        );
+        set_rom("tilemap", &self.tilemap_rom);
+        kf.set_boolean("tilemap", "mode7", self.tilemap_mode7);
+        kf.set_string("view", "tile_bpp", &format!("{:?}", self.tile_bpp));
+        kf.set_string("view", "tile_size", &format!("{:?}", self.tile_size));
+        kf.set_string("view", "bg_mode", &format!("{:?}", self.bg_mode));
+        kf.set_string("view", "zoom", &format!("{:?}", self.zoom));
+
+        kf.save_to_file(path)
+            .map_err(|e| std::io::Error::other(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rel(path: &str, base: &str) -> PathBuf {
+        relative_path(Path::new(path), Path::new(base))
+    }
+
+    #[test]
+    fn inside_project_dir() {
+        assert_eq!(rel("/proj/gfx/a.chr", "/proj"), PathBuf::from("gfx/a.chr"));
+        assert_eq!(rel("/proj/a.pal", "/proj"), PathBuf::from("a.pal"));
+    }
+
+    #[test]
+    fn parent_dir() {
+        assert_eq!(rel("/proj/a.pal", "/proj/sub"), PathBuf::from("../a.pal"));
+        assert_eq!(
+            rel("/proj/gfx/a.map", "/proj/sub/dir"),
+            PathBuf::from("../../gfx/a.map")
+        );
+    }
+
+    #[test]
+    fn outside_project_dir() {
+        // only the root is shared
+        assert_eq!(
+            rel("/data/game.sfc", "/home/proj"),
+            PathBuf::from("../../data/game.sfc")
+        );
+        // nothing is shared, keep the path as it is
+        assert_eq!(
+            rel("/data/game.sfc", "proj"),
+            PathBuf::from("/data/game.sfc")
+        );
+    }
+}
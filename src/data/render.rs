@@ -0,0 +1,328 @@
+use super::image::Rgba;
+use super::list_items::{Bpp, TileSize};
+use super::palette::Palette;
+use super::tilemap::{Tile, Tilemap};
+use super::tiles::Tileset;
+
+// what pixels with color 0 look like
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ColorZero {
+    // palette color 0, like the SNES shows it
+    #[default]
+    Backdrop,
+    Transparent,
+    // color 0 of the subpalette, like any other color
+    Subpalette,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RenderOptions {
+    pub bpp: Bpp,
+    // idx of palette 0 color 0
+    pub palette_base: usize,
+    pub tile_size: TileSize,
+    pub color_zero: ColorZero,
+    // draw tiles missing from the tileset pink instead of blank
+    pub mark_missing: bool,
+}
+
+const MISSING: [u8; 4] = [255, 204, 204, 255];
+const MISSING_DOT: [u8; 4] = [255, 179, 179, 255];
+
+impl RenderOptions {
+    // idx of color 0 of subpalette s
+    fn color_zero_idx(&self, s: u8) -> usize {
+        match self.bpp {
+            // single 256-color palette, palette bits of the tile are ignored
+            Bpp::Eight => 0,
+            bpp => self.palette_base + s as usize * bpp.to_val() as usize,
+        }
+    }
+
+    fn background(&self, palette: &Palette) -> [u8; 4] {
+        match self.color_zero {
+            ColorZero::Transparent => [0, 0, 0, 0],
+            ColorZero::Backdrop | ColorZero::Subpalette => {
+                let (r, g, b) = palette.0[0].to_rgb24();
+                [r, g, b, 255]
+            }
+        }
+    }
+}
+
+// draws into image with the same tileset, palette and options throughout
+struct Painter<'a> {
+    image: Rgba,
+    tileset: &'a Tileset,
+    palette: &'a Palette,
+    options: RenderOptions,
+}
+
+impl<'a> Painter<'a> {
+    fn new(
+        tileset: &'a Tileset,
+        palette: &'a Palette,
+        options: RenderOptions,
+        (width, height): (usize, usize),
+    ) -> Self {
+        let fill = options.background(palette);
+        Self {
+            image: Rgba {
+                pixels: vec![fill; width * height],
+                width,
+                height,
+            },
+            tileset,
+            palette,
+            options,
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        self.image.pixels[y * self.image.width + x] = color;
+    }
+
+    // draw 8x8 tile idx with its top left corner at (x, y)
+    fn put_tile(
+        &mut self,
+        idx: usize,
+        color_zero_idx: usize,
+        (x, y): (usize, usize),
+        (x_flip, y_flip): (bool, bool),
+    ) {
+        let Some(tile) = self.tileset.0.get(idx) else {
+            if self.options.mark_missing {
+                // pink tile with dot at the center
+                for i in 0..64 {
+                    let dot = (3..5).contains(&(i % 8)) && (3..5).contains(&(i / 8));
+                    self.set(
+                        x + i % 8,
+                        y + i / 8,
+                        if dot { MISSING_DOT } else { MISSING },
+                    );
+                }
+            }
+            return;
+        };
+        for (i, c) in tile.flipped(x_flip, y_flip).0.into_iter().enumerate() {
+            if c == 0 && self.options.color_zero != ColorZero::Subpalette {
+                continue;
+            }
+            // fail silently if c is out of range (>=4 for 2bpp, >=16 for 4bpp)
+            if c as u16 >= self.options.bpp.to_val() {
+                continue;
+            }
+            if let Some(color) = self.palette.0.get(color_zero_idx + c as usize) {
+                let (r, g, b) = color.to_rgb24();
+                self.set(x + i % 8, y + i / 8, [r, g, b, 255]);
+            }
+        }
+    }
+
+    // draw an 8x8 or 16x16 tilemap entry with its top left corner at (x, y)
+    fn put_entry(&mut self, tile: &Tile, (x, y): (usize, usize)) {
+        let color_zero_idx = self.options.color_zero_idx(tile.palette());
+        let flip = (tile.x_flip(), tile.y_flip());
+        let idx = tile.tile_idx() as usize;
+        match self.options.tile_size {
+            TileSize::Eight => self.put_tile(idx, color_zero_idx, (x, y), flip),
+            // 16x16 tiles are made of tiles n, n+1, n+16 and n+17, flipping swaps them too
+            TileSize::Sixteen => {
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let tx = if flip.0 { 1 - dx } else { dx };
+                    let ty = if flip.1 { 1 - dy } else { dy };
+                    let pos = (x + tx * 8, y + ty * 8);
+                    self.put_tile(idx + dy * 16 + dx, color_zero_idx, pos, flip);
+                }
+            }
+        }
+    }
+}
+
+// draw tilemap at 1 pixel per tile pixel
+pub fn render_tilemap(
+    tileset: &Tileset,
+    palette: &Palette,
+    tilemap: &Tilemap,
+    options: RenderOptions,
+) -> Rgba {
+    let cell = options.tile_size as usize;
+    let size = (tilemap.width * cell, tilemap.height * cell);
+    let mut painter = Painter::new(tileset, palette, options, size);
+    for (i, tile) in tilemap.tiles.iter().enumerate() {
+        let (ix, iy) = (i % tilemap.width, i / tilemap.width);
+        painter.put_entry(tile, (ix * cell, iy * cell));
+    }
+    painter.image
+}
+
+// draw 8x8 tiles in rows of columns tiles, all with the palette starting at color_zero_idx
+pub fn render_tiles(
+    tileset: &Tileset,
+    palette: &Palette,
+    indices: &[usize],
+    columns: usize,
+    color_zero_idx: usize,
+    options: RenderOptions,
+) -> Rgba {
+    let rows = indices.len().div_ceil(columns).max(1);
+    let mut painter = Painter::new(tileset, palette, options, (columns * 8, rows * 8));
+    for (i, &idx) in indices.iter().enumerate() {
+        let pos = ((i % columns) * 8, (i / columns) * 8);
+        painter.put_tile(idx, color_zero_idx, pos, (false, false));
+    }
+    painter.image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::color::Color;
+    use crate::data::tiles::TileData;
+
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    // every color is different
+    fn palette() -> Palette {
+        Palette(std::array::from_fn(|i| {
+            Color::new()
+                .with_red(i as u8 & 0b11111)
+                .with_green((i >> 5) as u8)
+        }))
+    }
+
+    fn rgba(palette: &Palette, idx: usize) -> [u8; 4] {
+        let (r, g, b) = palette.0[idx].to_rgb24();
+        [r, g, b, 255]
+    }
+
+    fn options(bpp: Bpp, tile_size: TileSize) -> RenderOptions {
+        RenderOptions {
+            bpp,
+            palette_base: 0,
+            tile_size,
+            color_zero: ColorZero::Transparent,
+            mark_missing: false,
+        }
+    }
+
+    // one tile per entry, filled with a single color
+    fn solid_tiles(colors: &[u8]) -> Tileset {
+        Tileset(colors.iter().map(|&c| TileData([c; 64])).collect())
+    }
+
+    fn single(tile: Tile) -> Tilemap {
+        let mut tilemap = Tilemap::new(1, 1);
+        tilemap.tiles[0] = tile;
+        tilemap
+    }
+
+    #[test]
+    fn flips() {
+        let palette = palette();
+        // only the top left pixel is set
+        let mut pixels = [0; 64];
+        pixels[0] = 1;
+        let tileset = Tileset(vec![TileData(pixels)]);
+
+        for (x_flip, y_flip, pos) in [
+            (false, false, 0),
+            (true, false, 7),
+            (false, true, 56),
+            (true, true, 63),
+        ] {
+            let tile = Tile::new().with_x_flip(x_flip).with_y_flip(y_flip);
+            let image = render_tilemap(
+                &tileset,
+                &palette,
+                &single(tile),
+                options(Bpp::Four, TileSize::Eight),
+            );
+            let mut expected = vec![CLEAR; 64];
+            expected[pos] = rgba(&palette, 1);
+            assert_eq!(image.pixels, expected, "flip {x_flip} {y_flip}");
+        }
+    }
+
+    #[test]
+    fn large_tiles() {
+        let palette = palette();
+        // tiles 0, 1, 16 and 17 make up the 16x16 tile
+        let mut colors = [0; 18];
+        colors[..4].copy_from_slice(&[1, 2, 0, 0]);
+        colors[16..].copy_from_slice(&[3, 4]);
+        let tileset = solid_tiles(&colors);
+
+        // color at the top left of each quadrant, left to right, top to bottom
+        for (x_flip, y_flip, quadrants) in [
+            (false, false, [1, 2, 3, 4]),
+            (true, false, [2, 1, 4, 3]),
+            (false, true, [3, 4, 1, 2]),
+            (true, true, [4, 3, 2, 1]),
+        ] {
+            let tile = Tile::new().with_x_flip(x_flip).with_y_flip(y_flip);
+            let image = render_tilemap(
+                &tileset,
+                &palette,
+                &single(tile),
+                options(Bpp::Four, TileSize::Sixteen),
+            );
+            assert_eq!((image.width, image.height), (16, 16));
+            for (i, pos) in [0, 8, 16 * 8, 16 * 8 + 8].into_iter().enumerate() {
+                assert_eq!(
+                    image.pixels[pos],
+                    rgba(&palette, quadrants[i]),
+                    "flip {x_flip} {y_flip}, quadrant {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn palette_base_2bpp() {
+        let palette = palette();
+        let tileset = solid_tiles(&[1]);
+        let tile = Tile::new().with_palette(3);
+        let options = RenderOptions {
+            // BG2 in mode 0
+            palette_base: 32,
+            ..options(Bpp::Two, TileSize::Eight)
+        };
+        let image = render_tilemap(&tileset, &palette, &single(tile), options);
+        // 32 + 3 * 4 + 1
+        assert_eq!(image.pixels[0], rgba(&palette, 45));
+    }
+
+    #[test]
+    fn palette_ignored_8bpp() {
+        let palette = palette();
+        let tileset = solid_tiles(&[200]);
+        let tile = Tile::new().with_palette(5);
+        let image = render_tilemap(
+            &tileset,
+            &palette,
+            &single(tile),
+            options(Bpp::Eight, TileSize::Eight),
+        );
+        assert_eq!(image.pixels[0], rgba(&palette, 200));
+    }
+
+    #[test]
+    fn color_zero() {
+        let palette = palette();
+        let tileset = solid_tiles(&[0]);
+        let tilemap = single(Tile::new().with_palette(2));
+        for (color_zero, expected) in [
+            (ColorZero::Transparent, CLEAR),
+            (ColorZero::Backdrop, rgba(&palette, 0)),
+            (ColorZero::Subpalette, rgba(&palette, 32)),
+        ] {
+            let options = RenderOptions {
+                color_zero,
+                ..options(Bpp::Four, TileSize::Eight)
+            };
+            let image = render_tilemap(&tileset, &palette, &tilemap, options);
+            assert_eq!(image.pixels, vec![expected; 64], "{color_zero:?}");
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use strum::{EnumIter, EnumString};
+
+use super::compression::Compression;
+
+// size of the copier header some .smc files start with
+const COPIER_HEADER: usize = 512;
+
+// how the address typed by the user is read
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AddressKind {
+    // offset into the rom data, not counting the copier header
+    #[default]
+    Offset,
+    LoROM,
+    HiROM,
+}
+
+impl std::fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressKind::Offset => write!(f, "ROM offset"),
+            AddressKind::LoROM => write!(f, "LoROM address"),
+            AddressKind::HiROM => write!(f, "HiROM address"),
+        }
+    }
+}
+
+impl AddressKind {
+    // convert to offset into the rom data
+    pub fn to_offset(&self, addr: usize) -> Option<usize> {
+        let (bank, low) = ((addr >> 16) & 0xFF, addr & 0xFFFF);
+        match self {
+            AddressKind::Offset => Some(addr),
+            // banks 7E-7F are WRAM
+            _ if bank == 0x7E || bank == 0x7F => None,
+            // 32KiB per bank at 8000-FFFF
+            AddressKind::LoROM if low >= 0x8000 => Some((bank & 0x7F) * 0x8000 + low - 0x8000),
+            // 64KiB per bank at 40-7D and C0-FF, upper halves mirrored in 00-3F and 80-BF
+            AddressKind::HiROM if bank & 0x40 != 0 || low >= 0x8000 => {
+                Some((bank & 0x3F) * 0x10000 + low)
+            }
+            AddressKind::LoROM | AddressKind::HiROM => None,
+        }
+    }
+}
+
+// place in a rom file an asset was loaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomRegion {
+    pub path: PathBuf,
+    // offset into the file, including the copier header
+    pub offset: usize,
+    // size of the copier header, 0 if there is none
+    pub header: usize,
+    // bytes available for the (compressed) data
+    pub len: usize,
+    // size of the data after decompressing, editors may pad it
+    pub data_len: usize,
+}
+
+fn header_size(file_len: usize) -> usize {
+    if file_len % 1024 == COPIER_HEADER {
+        COPIER_HEADER
+    } else {
+        0
+    }
+}
+
+impl RomRegion {
+    // read data at addr, len is ignored for compressed data
+    pub fn read(
+        path: &PathBuf,
+        kind: AddressKind,
+        addr: usize,
+        len: usize,
+        compression: Compression,
+    ) -> std::io::Result<(Self, Vec<u8>)> {
+        let content = std::fs::read(path)?;
+        let Some(offset) = kind.to_offset(addr) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("${addr:06X} is not a {kind}"),
+            ));
+        };
+        let header = header_size(content.len());
+        let offset = offset + header;
+        if offset >= content.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("offset {offset:#X} is past the end of the file"),
+            ));
+        }
+
+        let (data, len) = if compression == Compression::None {
+            if offset + len > content.len() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{len} bytes at {offset:#X} is past the end of the file"),
+                ));
+            }
+            (content[offset..offset + len].to_vec(), len)
+        } else {
+            compression.decompress_len(&content[offset..])?
+        };
+
+        let region = Self {
+            path: path.clone(),
+            offset,
+            header,
+            len,
+            data_len: data.len(),
+        };
+        Ok((region, data))
+    }
+
+    // read the same place again
+    pub fn reload(&self, compression: Compression) -> std::io::Result<Vec<u8>> {
+        let content = std::fs::read(&self.path)?;
+        let end = (self.offset + self.len).min(content.len());
+        let Some(region) = content.get(self.offset..end) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file is smaller than when it was opened",
+            ));
+        };
+        compression.decompress(region)
+    }
+
+    // overwrite the original data, bytes past the new data are left as is
+    // data is cut back to the size that was read, padding past it must still be 0
+    pub fn patch(&self, data: &[u8], compression: Compression) -> std::io::Result<()> {
+        let (data, rest) = data.split_at(self.data_len.min(data.len()));
+        if rest.iter().any(|&b| b != 0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "edited data goes past the {} bytes read from {:#X}",
+                    self.data_len, self.offset
+                ),
+            ));
+        }
+        let content = compression.compress(data);
+        if content.len() > self.len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "data is {} bytes but only {} bytes are available at {:#X}",
+                    content.len(),
+                    self.len,
+                    self.offset
+                ),
+            ));
+        }
+
+        let mut f = OpenOptions::new().write(true).open(&self.path)?;
+        f.seek(SeekFrom::Start(self.offset as u64))?;
+        f.write_all(&content)
+    }
+}
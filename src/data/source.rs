@@ -0,0 +1,279 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+
+use strum::{EnumIter, EnumString};
+
+use super::{list_items::Bpp, palette::Palette, tilemap::Tilemap, tiles::Tileset};
+
+// assembler syntax of exported source files
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Assembler {
+    #[default]
+    Asar,
+    Ca65,
+    WLA,
+}
+
+impl std::fmt::Display for Assembler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Assembler::Asar => write!(f, "asar"),
+            Assembler::Ca65 => write!(f, "ca65"),
+            Assembler::WLA => write!(f, "WLA-DX"),
+        }
+    }
+}
+
+impl Assembler {
+    fn directive(&self, word: bool) -> &'static str {
+        match (self, word) {
+            (Assembler::Asar, false) => "db",
+            (Assembler::Asar, true) => "dw",
+            (Assembler::Ca65, false) => ".byte",
+            (Assembler::Ca65, true) => ".word",
+            (Assembler::WLA, false) => ".db",
+            (Assembler::WLA, true) => ".dw",
+        }
+    }
+
+    fn constant(&self, name: &str, value: &str) -> String {
+        match self {
+            Assembler::Asar | Assembler::Ca65 => format!("{name} = {value}"),
+            Assembler::WLA => format!(".DEFINE {name} {value}"),
+        }
+    }
+}
+
+// turn any text into a valid label or C identifier
+pub fn identifier(s: &str) -> String {
+    let mut ident: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+pub fn label_from_path(path: &std::path::Path) -> String {
+    identifier(
+        &path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    )
+}
+
+// one asset laid out as lines of values, with a comment before each group of values
+struct Block {
+    content: Vec<u8>,
+    // colors, tiles or tilemap entries
+    count: usize,
+    // little endian 16-bit values instead of bytes
+    word: bool,
+    per_line: usize,
+    group: usize,
+    group_comment: Box<dyn Fn(usize) -> String>,
+}
+
+impl Block {
+    fn palette(palette: &Palette) -> Self {
+        // 8 colors per line, comment per row of 16 colors
+        Self {
+            content: palette.to_bytes_bgr555(),
+            count: palette.0.len(),
+            word: true,
+            per_line: 8,
+            group: 16,
+            group_comment: Box::new(|i| format!("row {i}")),
+        }
+    }
+
+    fn tileset(tileset: &Tileset, bpp: Bpp, mode7: bool) -> Self {
+        let (content, tile_len) = if mode7 {
+            (tileset.to_bytes_mode7(), 64)
+        } else {
+            (tileset.to_bytes(bpp), 8 * bpp.bits() as usize)
+        };
+        // 16 bytes per line, comment per tile
+        Self {
+            content,
+            count: tileset.0.len(),
+            word: false,
+            per_line: 16,
+            group: tile_len,
+            group_comment: Box::new(|i| format!("tile ${i:03X}")),
+        }
+    }
+
+    fn tilemap(tilemap: &Tilemap, mode7: bool) -> std::io::Result<Self> {
+        let count = tilemap.tiles.len();
+        Ok(if mode7 {
+            // 1 byte per entry, comment per row of 128 entries
+            Self {
+                content: tilemap.to_bytes_mode7()?,
+                count,
+                word: false,
+                per_line: 16,
+                group: Tilemap::MODE7_SIZE,
+                group_comment: Box::new(|i| format!("row {i}")),
+            }
+        } else {
+            // stored in screen order, comment per row of a 32x32 screen
+            Self {
+                content: tilemap.to_bytes(),
+                count,
+                word: true,
+                per_line: 16,
+                group: 32,
+                group_comment: Box::new(|i| format!("screen {} row {}", i / 32, i % 32)),
+            }
+        })
+    }
+
+    // number of values
+    fn len(&self) -> usize {
+        if self.word {
+            self.content.len().div_ceil(2)
+        } else {
+            self.content.len()
+        }
+    }
+
+    // values formatted with a hex prefix, grouped in lines
+    // (comment if the line starts a group, values)
+    fn lines(&self, prefix: &str) -> Vec<(Option<String>, Vec<String>)> {
+        let values: Vec<String> = if self.word {
+            self.content
+                .chunks(2)
+                .map(|w| u16::from_le_bytes([w[0], *w.get(1).unwrap_or(&0)]))
+                .map(|w| format!("{prefix}{w:04X}"))
+                .collect()
+        } else {
+            self.content
+                .iter()
+                .map(|b| format!("{prefix}{b:02X}"))
+                .collect()
+        };
+
+        values
+            .chunks(self.per_line)
+            .enumerate()
+            .map(|(i, line)| {
+                let first = i * self.per_line;
+                let comment = first
+                    .is_multiple_of(self.group)
+                    .then(|| (self.group_comment)(first / self.group));
+                (comment, line.to_vec())
+            })
+            .collect()
+    }
+}
+
+pub struct Source {
+    pub asm: Assembler,
+    pub label: String,
+    // comment before each tile or row
+    pub comments: bool,
+}
+
+impl Source {
+    // _SIZE and _COUNT constants, label, then the data as lines of db/dw values
+    fn write_block(&self, mut f: &File, block: Block) -> std::io::Result<()> {
+        let label = &self.label;
+        let mut s = String::new();
+        let size = format!("${:04X}", block.content.len());
+        let _ = writeln!(s, "{}", self.asm.constant(&format!("{label}_SIZE"), &size));
+        let count = block.count.to_string();
+        let _ = writeln!(
+            s,
+            "{}",
+            self.asm.constant(&format!("{label}_COUNT"), &count)
+        );
+        let _ = writeln!(s);
+        let _ = writeln!(s, "{label}:");
+        for (comment, line) in block.lines("$") {
+            if let Some(comment) = comment.filter(|_| self.comments) {
+                let _ = writeln!(s, "; {comment}");
+            }
+            let _ = writeln!(s, "\t{} {}", self.asm.directive(block.word), line.join(","));
+        }
+        f.write_all(s.as_bytes())
+    }
+
+    pub fn write_palette(&self, palette: &Palette, f: &File) -> std::io::Result<()> {
+        self.write_block(f, Block::palette(palette))
+    }
+
+    pub fn write_tileset(
+        &self,
+        tileset: &Tileset,
+        bpp: Bpp,
+        mode7: bool,
+        f: &File,
+    ) -> std::io::Result<()> {
+        self.write_block(f, Block::tileset(tileset, bpp, mode7))
+    }
+
+    pub fn write_tilemap(&self, tilemap: &Tilemap, mode7: bool, f: &File) -> std::io::Result<()> {
+        self.write_block(f, Block::tilemap(tilemap, mode7)?)
+    }
+}
+
+// C header with a static const array
+pub struct CHeader {
+    pub name: String,
+    // comment before each tile or row
+    pub comments: bool,
+}
+
+impl CHeader {
+    // include guard, _SIZE/_LEN/_COUNT defines, then the array
+    fn write_block(&self, mut f: &File, block: Block) -> std::io::Result<()> {
+        let name = &self.name;
+        let guard = format!("{}_H", name.to_uppercase());
+        let c_type = if block.word { "uint16_t" } else { "uint8_t" };
+
+        let mut s = String::new();
+        let _ = writeln!(s, "#ifndef {guard}");
+        let _ = writeln!(s, "#define {guard}");
+        let _ = writeln!(s);
+        let _ = writeln!(s, "#include <stdint.h>");
+        let _ = writeln!(s);
+        let _ = writeln!(s, "#define {name}_SIZE {}", block.content.len());
+        let _ = writeln!(s, "#define {name}_LEN {}", block.len());
+        let _ = writeln!(s, "#define {name}_COUNT {}", block.count);
+        let _ = writeln!(s);
+        let _ = writeln!(s, "static const {c_type} {name}[{name}_LEN] = {{");
+        for (comment, line) in block.lines("0x") {
+            if let Some(comment) = comment.filter(|_| self.comments) {
+                let _ = writeln!(s, "    /* {comment} */");
+            }
+            let _ = writeln!(s, "    {},", line.join(", "));
+        }
+        let _ = writeln!(s, "}};");
+        let _ = writeln!(s);
+        let _ = writeln!(s, "#endif");
+        f.write_all(s.as_bytes())
+    }
+
+    pub fn write_palette(&self, palette: &Palette, f: &File) -> std::io::Result<()> {
+        self.write_block(f, Block::palette(palette))
+    }
+
+    pub fn write_tileset(
+        &self,
+        tileset: &Tileset,
+        bpp: Bpp,
+        mode7: bool,
+        f: &File,
+    ) -> std::io::Result<()> {
+        self.write_block(f, Block::tileset(tileset, bpp, mode7))
+    }
+
+    pub fn write_tilemap(&self, tilemap: &Tilemap, mode7: bool, f: &File) -> std::io::Result<()> {
+        self.write_block(f, Block::tilemap(tilemap, mode7)?)
+    }
+}
@@ -1,8 +1,11 @@
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 
+use modular_bitfield::error::OutOfBounds;
 use modular_bitfield::prelude::*;
 
+use super::list_items::TilemapSize;
+
 #[bitfield]
 #[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct Tile {
@@ -13,17 +16,170 @@ pub struct Tile {
     pub y_flip: bool,
 }
 
-pub struct Tilemap(pub [Tile; 1024]);
+// rectangle of tiles taken out of a tilemap, for copy and paste or as a stamp brush
+#[derive(Clone)]
+pub struct TileBlock {
+    pub tiles: Vec<Tile>,
+    // size in tiles
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TileBlock {
+    // cells and tiles when the top left corner is placed at (x, y)
+    pub fn cells_at(
+        &self,
+        (x, y): (usize, usize),
+    ) -> impl Iterator<Item = ((usize, usize), Tile)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(move |(i, t)| ((x + i % self.width, y + i / self.width), *t))
+    }
+
+    // tileset tiles tl to br (inclusive corners) as laid out in the tile picker, 16 per row,
+    // step is 2 when each tile is a 16x16 tile made of 2x2 tileset tiles
+    pub fn from_tile_range(tl: u32, br: u32, step: u32, palette: u8) -> Result<Self, OutOfBounds> {
+        let (width, height) = (
+            ((br % 16 - tl % 16) / step + 1) as usize,
+            ((br / 16 - tl / 16) / step + 1) as usize,
+        );
+        let mut tiles = Vec::new();
+        for y in 0..height as u32 {
+            for x in 0..width as u32 {
+                let mut tile = Tile::default();
+                tile.set_tile_idx_checked((tl + (y * 16 + x) * step) as u16)?;
+                tile.set_palette(palette);
+                tiles.push(tile);
+            }
+        }
+        Ok(Self {
+            tiles,
+            width,
+            height,
+        })
+    }
+
+    // mirrored block, each tile is flipped as well
+    pub fn flipped(&self, x_flip: bool, y_flip: bool) -> Self {
+        let (w, h) = (self.width, self.height);
+        let tiles = (0..w * h)
+            .map(|i| {
+                let x = if x_flip { w - 1 - i % w } else { i % w };
+                let y = if y_flip { h - 1 - i / w } else { i / w };
+                let mut tile = self.tiles[y * w + x];
+                tile.set_x_flip(tile.x_flip() != x_flip);
+                tile.set_y_flip(tile.y_flip() != y_flip);
+                tile
+            })
+            .collect();
+        Self {
+            tiles,
+            width: w,
+            height: h,
+        }
+    }
+
+    // offset of (x, y) inside the block when it is repeated in all directions from origin
+    fn offset(&self, origin: (usize, usize), (x, y): (usize, usize)) -> (usize, usize) {
+        (
+            (x as isize - origin.0 as isize).rem_euclid(self.width as isize) as usize,
+            (y as isize - origin.1 as isize).rem_euclid(self.height as isize) as usize,
+        )
+    }
+
+    pub fn tile_from(&self, origin: (usize, usize), pos: (usize, usize)) -> Tile {
+        let (dx, dy) = self.offset(origin, pos);
+        self.tiles[dy * self.width + dx]
+    }
+
+    // cells of the repeated block covering (x, y), clipped to a width by height tilemap
+    pub fn copy_covering(
+        &self,
+        origin: (usize, usize),
+        (x, y): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let (dx, dy) = self.offset(origin, (x, y));
+        let (left, top) = (x as isize - dx as isize, y as isize - dy as isize);
+        (0..self.width * self.height)
+            .map(|i| {
+                (
+                    left + (i % self.width) as isize,
+                    top + (i / self.width) as isize,
+                )
+            })
+            .filter(|&(cx, cy)| {
+                (0..width as isize).contains(&cx) && (0..height as isize).contains(&cy)
+            })
+            .map(|(cx, cy)| (cx as usize, cy as usize))
+            .collect()
+    }
+}
+
+pub struct Tilemap {
+    pub tiles: Vec<Tile>,
+    // size in tiles
+    pub width: usize,
+    pub height: usize,
+}
 
 impl Default for Tilemap {
     fn default() -> Self {
-        Self([Tile::default(); 1024])
+        Self::new(32, 32)
     }
 }
 
 impl Tilemap {
-    pub fn from_file(path: &std::path::PathBuf) -> std::io::Result<Self> {
-        let content = std::fs::read(&path)?;
+    // width and height of a mode 7 tilemap
+    pub const MODE7_SIZE: usize = 128;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            tiles: vec![Tile::default(); width * height],
+            width,
+            height,
+        }
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> Option<Tile> {
+        if x < self.width && y < self.height {
+            Some(self.tiles[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    // tiles between corners start and end (inclusive), clipped to the tilemap
+    pub fn copy_rect(&self, start: (usize, usize), end: (usize, usize)) -> TileBlock {
+        let (x_min, y_min) = (start.0.min(end.0), start.1.min(end.1));
+        let x_max = start.0.max(end.0).min(self.width.saturating_sub(1));
+        let y_max = start.1.max(end.1).min(self.height.saturating_sub(1));
+        let (width, height) = (
+            (x_max + 1).saturating_sub(x_min),
+            (y_max + 1).saturating_sub(y_min),
+        );
+        let tiles = (0..width * height)
+            .map(|i| self.tiles[(y_min + i / width) * self.width + x_min + i % width])
+            .collect();
+        TileBlock {
+            tiles,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_file(path: &std::path::PathBuf, size: TilemapSize) -> std::io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?, size)
+    }
+
+    pub fn from_reader(mut reader: impl Read, size: TilemapSize) -> std::io::Result<Self> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Self::from_bytes(&content, size)
+    }
+
+    pub fn from_bytes(content: &[u8], size: TilemapSize) -> std::io::Result<Self> {
         let len = content.len();
         // check alignment
         if len % 2 != 0 {
@@ -33,26 +189,217 @@ impl Tilemap {
             ));
         }
         // check file size
-        if len > 32 * 32 * 2 {
+        let (width, height) = size.dims();
+        if len > width * height * 2 {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
-                "file must not exceed 2048 bytes",
+                format!("file must not exceed {} bytes", width * height * 2),
             ));
         }
 
-        Ok(Tilemap({
-            let mut v = [Tile::default(); 1024];
-            for i in (0..len).step_by(2) {
-                v[i / 2] = Tile::from_bytes([content[i], content[i + 1]]);
-            }
-            v
-        }))
+        let mut tilemap = Self::new(width, height);
+        for i in (0..len).step_by(2) {
+            let idx = tilemap.screen_order_idx(i / 2);
+            tilemap.tiles[idx] = Tile::from_bytes([content[i], content[i + 1]]);
+        }
+        Ok(tilemap)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        (0..self.tiles.len())
+            .flat_map(|i| self.tiles[self.screen_order_idx(i)].into_bytes())
+            .collect()
+    }
+
+    pub fn to_writer(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn write_to_file(&self, f: &File) -> std::io::Result<()> {
+        self.to_writer(f)
+    }
+
+    // tilemaps larger than 32x32 are stored one 32x32 screen after another,
+    // convert the i-th stored tile to its index in self.tiles
+    fn screen_order_idx(&self, i: usize) -> usize {
+        let screens_w = (self.width / 32).max(1);
+        let (screen, within) = (i / 1024, i % 1024);
+        let x = (screen % screens_w) * 32 + within % 32;
+        let y = (screen / screens_w) * 32 + within / 32;
+        y * self.width + x
     }
 
-    pub fn write_to_file(&self, mut f: &File) -> std::io::Result<()> {
-        for c in self.0 {
-            f.write_all(&c.into_bytes())?;
+    pub fn size(&self) -> Option<TilemapSize> {
+        TilemapSize::from_dims(self.width, self.height)
+    }
+
+    // mode 7 tilemap: 128x128 tiles, 1 byte (tile index) per tile
+    pub fn from_bytes_mode7(content: &[u8]) -> std::io::Result<Self> {
+        let size = Self::MODE7_SIZE;
+        if content.len() > size * size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("mode 7 tilemap must not exceed {} bytes", size * size),
+            ));
         }
-        Ok(())
+
+        let mut tilemap = Self::new(size, size);
+        for (tile, idx) in tilemap.tiles.iter_mut().zip(content) {
+            tile.set_tile_idx(*idx as u16);
+        }
+        Ok(tilemap)
+    }
+
+    // mode 7 entries are only a tile index up to 255, other tiles can't be stored
+    pub fn to_bytes_mode7(&self) -> std::io::Result<Vec<u8>> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let idx = t.tile_idx();
+                if idx <= 0xFF && *t == Tile::new().with_tile_idx(idx) {
+                    return Ok(idx as u8);
+                }
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "tile at ({}, {}) doesn't fit a mode 7 tilemap, which stores tile 0-255 without flips, palette or priority",
+                        i % self.width,
+                        i / self.width
+                    ),
+                ))
+            })
+            .collect()
+    }
+
+    pub fn from_reader_mode7(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Self::from_bytes_mode7(&content)
+    }
+
+    pub fn to_writer_mode7(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes_mode7()?)
+    }
+
+    pub fn from_file_mode7(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_bytes_mode7(&std::fs::read(path)?)
+    }
+
+    pub fn write_file_mode7(&self, f: &File) -> std::io::Result<()> {
+        self.to_writer_mode7(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(idx: u16) -> Tile {
+        Tile::new().with_tile_idx(idx)
+    }
+
+    // tiles 0 to width * height - 1, left to right, top to bottom
+    fn block(width: usize, height: usize) -> TileBlock {
+        TileBlock {
+            tiles: (0..width * height).map(|i| tile(i as u16)).collect(),
+            width,
+            height,
+        }
+    }
+
+    fn indices(block: &TileBlock) -> Vec<u16> {
+        block.tiles.iter().map(|t| t.tile_idx()).collect()
+    }
+
+    #[test]
+    fn flipped() {
+        let mut block = block(2, 2);
+        block.tiles[1].set_x_flip(true);
+
+        let x = block.flipped(true, false);
+        assert_eq!(indices(&x), [1, 0, 3, 2]);
+        let x_flips: Vec<_> = x.tiles.iter().map(|t| t.x_flip()).collect();
+        // tile 1 was already flipped
+        assert_eq!(x_flips, [false, true, true, true]);
+        assert!(x.tiles.iter().all(|t| !t.y_flip()));
+
+        let y = block.flipped(false, true);
+        assert_eq!(indices(&y), [2, 3, 0, 1]);
+        assert!(y.tiles.iter().all(|t| t.y_flip()));
+        let x_flips: Vec<_> = y.tiles.iter().map(|t| t.x_flip()).collect();
+        assert_eq!(x_flips, [false, false, false, true]);
+
+        let xy = block.flipped(true, true);
+        assert_eq!(indices(&xy), [3, 2, 1, 0]);
+        assert_eq!((xy.width, xy.height), (2, 2));
+    }
+
+    #[test]
+    fn tile_from_repeats() {
+        let block = block(3, 2);
+        let origin = (5, 5);
+        for (pos, idx) in [
+            ((5, 5), 0),
+            ((7, 6), 5),
+            // wraps back to the origin
+            ((8, 7), 0),
+            // left of and above the origin
+            ((4, 5), 2),
+            ((3, 4), 4),
+            ((0, 0), 4),
+        ] {
+            assert_eq!(block.tile_from(origin, pos).tile_idx(), idx, "{pos:?}");
+        }
+    }
+
+    #[test]
+    fn copy_covering_clips() {
+        let block = block(3, 2);
+        // whole copy inside the tilemap
+        assert_eq!(
+            block.copy_covering((1, 1), (2, 2), (8, 8)),
+            [(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2)]
+        );
+        // copy starts at (-2, -1)
+        assert_eq!(block.copy_covering((1, 1), (0, 0), (4, 4)), [(0, 0)]);
+        // copy starts at (1, 3), second row is past the bottom edge
+        assert_eq!(
+            block.copy_covering((1, 1), (3, 3), (4, 4)),
+            [(1, 3), (2, 3), (3, 3)]
+        );
+    }
+
+    #[test]
+    fn mode7_entries() {
+        let mut tilemap = Tilemap::new(2, 1);
+        tilemap.tiles[0] = tile(255);
+        assert_eq!(tilemap.to_bytes_mode7().unwrap(), [255, 0]);
+
+        // too large for a byte
+        tilemap.tiles[1] = tile(300);
+        assert!(tilemap.to_bytes_mode7().is_err());
+        // attributes aren't stored
+        tilemap.tiles[1] = tile(1).with_x_flip(true);
+        assert!(tilemap.to_bytes_mode7().is_err());
+        tilemap.tiles[1] = tile(1).with_palette(2);
+        assert!(tilemap.to_bytes_mode7().is_err());
+    }
+
+    #[test]
+    fn from_tile_range() {
+        // rows 1-2, columns 1-3
+        let block = TileBlock::from_tile_range(17, 35, 1, 3).unwrap();
+        assert_eq!((block.width, block.height), (3, 2));
+        assert_eq!(indices(&block), [17, 18, 19, 33, 34, 35]);
+        assert!(block.tiles.iter().all(|t| t.palette() == 3));
+
+        // 16x16 tiles start every 2 tiles and every 2 rows
+        let block = TileBlock::from_tile_range(0, 34, 2, 0).unwrap();
+        assert_eq!((block.width, block.height), (2, 2));
+        assert_eq!(indices(&block), [0, 2, 32, 34]);
+
+        // past the last tile idx
+        assert!(TileBlock::from_tile_range(1022, 1039, 1, 0).is_err());
     }
 }
@@ -1,6 +1,7 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
 use crate::data::list_items::Bpp;
-use crate::widgets::window::Window;
-use crate::TILE_W;
 
 pub struct TileData(pub [u8; 64]);
 
@@ -54,35 +55,84 @@ impl TileData {
         Some(Self(chr))
     }
 
-    fn draw(&self, cr: &gtk::cairo::Context, state: &Window, palette_subset: Option<u8>) {
-        let pxl_w = TILE_W / 8.0;
-        // collect pixels with same color, then draw the pixels together
-        let mut rects = vec![Vec::new(); state.tile_bpp().to_val() as usize];
+    fn from_8bpp(s: &[u8]) -> Option<Self> {
+        // 8 * 8 pixels * 8 (bits/pixel) / 8 (bits/byte)
+        if s.len() != 64 {
+            return None;
+        }
+
+        // s = [bits 0 and 1 of each row, stored the same way as 2bpp,
+        //      bits 2 and 3 of each row,
+        //      bits 4 and 5 of each row,
+        //      bits 6 and 7 of each row]
+        let mut chr = [0; 64];
+        for (i, px) in chr.iter_mut().enumerate() {
+            let a = i / 8;
+            let b = i % 8;
+            for plane in 0..4 {
+                *px |= ((s[16 * plane + 2 * a] >> (7 - b)) & 0b1) << (2 * plane);
+                *px |= ((s[16 * plane + 2 * a + 1] >> (7 - b)) & 0b1) << (2 * plane + 1);
+            }
+        }
+        Some(Self(chr))
+    }
 
-        // (0, 0) as top left corner of tile
-        for (j, c) in self.0.into_iter().enumerate() {
-            // top left corner of pixel
-            let x_off = (j % 8) as f64 * pxl_w;
-            let y_off = (j / 8) as f64 * pxl_w;
-            // fail silently if c is out of range (>=4 for 2bpp, >=16 for 4bpp)
-            rects.get_mut(c as usize).map(|v| v.push((x_off, y_off)));
+    fn to_2bpp(&self) -> [u8; 16] {
+        // inverse of from_2bpp
+        let mut s = [0; 16];
+        for (i, c) in self.0.into_iter().enumerate() {
+            let a = i / 8;
+            let b = i % 8;
+            s[2 * a] |= (c & 0b1) << (7 - b); // bit 0
+            s[2 * a + 1] |= ((c >> 1) & 0b1) << (7 - b); // bit 1
         }
+        s
+    }
 
-        let palette_data = state.palette_data();
-        let color_zero_idx = if let Some(s) = palette_subset {
-            state.palette_base() + s * state.tile_bpp().to_val()
-        } else {
-            state.palette_sel_idx() - state.palette_sel_idx() % state.tile_bpp().to_val()
-        };
+    fn to_4bpp(&self) -> [u8; 32] {
+        // inverse of from_4bpp
+        let mut s = [0; 32];
+        for (i, c) in self.0.into_iter().enumerate() {
+            let a = i / 8;
+            let b = i % 8;
+            s[2 * a] |= (c & 0b1) << (7 - b); // bit 0
+            s[2 * a + 1] |= ((c >> 1) & 0b1) << (7 - b); // bit 1
+            s[16 + 2 * a] |= ((c >> 2) & 0b1) << (7 - b); // bit 2
+            s[16 + 2 * a + 1] |= ((c >> 3) & 0b1) << (7 - b); // bit 3
+        }
+        s
+    }
 
-        for (i, v) in rects.into_iter().enumerate() {
-            for (x, y) in v {
-                cr.rectangle(x, y, pxl_w, pxl_w);
+    fn to_8bpp(&self) -> [u8; 64] {
+        // inverse of from_8bpp
+        let mut s = [0; 64];
+        for (i, c) in self.0.into_iter().enumerate() {
+            let a = i / 8;
+            let b = i % 8;
+            for plane in 0..4 {
+                s[16 * plane + 2 * a] |= ((c >> (2 * plane)) & 0b1) << (7 - b);
+                s[16 * plane + 2 * a + 1] |= ((c >> (2 * plane + 1)) & 0b1) << (7 - b);
             }
-            let (r, g, b) = palette_data.0[color_zero_idx as usize + i].to_cairo();
-            cr.set_source_rgb(r, g, b);
-            let _ = cr.fill();
         }
+        s
+    }
+
+    fn from_bpp(s: &[u8], bpp: Bpp) -> Option<Self> {
+        match bpp {
+            Bpp::Two => Self::from_2bpp(s),
+            Bpp::Four => Self::from_4bpp(s),
+            Bpp::Eight => Self::from_8bpp(s),
+        }
+    }
+
+    pub fn flipped(&self, x_flip: bool, y_flip: bool) -> Self {
+        let mut chr = [0; 64];
+        for (i, c) in chr.iter_mut().enumerate() {
+            let x = if x_flip { 7 - i % 8 } else { i % 8 };
+            let y = if y_flip { 7 - i / 8 } else { i / 8 };
+            *c = self.0[y * 8 + x];
+        }
+        Self(chr)
     }
 }
 
@@ -95,10 +145,20 @@ impl Default for Tileset {
 }
 
 impl Tileset {
-    const MAX: usize = 0b1 << 10;
+    pub const MAX: usize = 0b1 << 10;
+    const MODE7_MAX: usize = 256;
 
     pub fn from_file(path: &std::path::PathBuf, bpp: Bpp) -> std::io::Result<Self> {
-        let content = std::fs::read(path)?;
+        Self::from_bytes(&std::fs::read(path)?, bpp)
+    }
+
+    pub fn from_reader(mut reader: impl Read, bpp: Bpp) -> std::io::Result<Self> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Self::from_bytes(&content, bpp)
+    }
+
+    pub fn from_bytes(content: &[u8], bpp: Bpp) -> std::io::Result<Self> {
         let len = content.len();
         if len == 0 {
             return Err(std::io::Error::new(
@@ -123,38 +183,107 @@ impl Tileset {
 
         let mut tiles = Vec::new();
         for i in (0..len).step_by(align) {
-            let tile = match bpp {
-                Bpp::Two => TileData::from_2bpp(&content[i..i + align]).unwrap(),
-                Bpp::Four => TileData::from_4bpp(&content[i..i + align]).unwrap(),
-            };
-            tiles.push(tile);
+            tiles.push(TileData::from_bpp(&content[i..i + align], bpp).unwrap());
         }
         Ok(Self(tiles))
     }
 
-    pub fn draw_tile(
-        &self,
-        idx: usize,
-        cr: &gtk::cairo::Context,
-        state: &Window,
-        palette_subset: Option<u8>,
-    ) {
-        if let Some(tile) = self.0.get(idx) {
-            tile.draw(cr, state, palette_subset);
-        } else {
-            // pink tile with dot at the center
-            cr.rectangle(0.0, 0.0, TILE_W, TILE_W);
-            cr.set_source_rgb(1.0, 0.8, 0.8);
-            let _ = cr.fill();
-            cr.arc(
-                TILE_W / 2.0,
-                TILE_W / 2.0,
-                TILE_W / 6.0,
-                0.0,
-                2.0 * std::f64::consts::PI,
-            );
-            cr.set_source_rgb(1.0, 0.7, 0.7);
-            let _ = cr.fill();
+    // decode up to count tiles starting at any byte offset, stopping at the end of the data
+    pub fn decode_at(content: &[u8], offset: usize, bpp: Bpp, count: usize) -> Self {
+        let align = 8 * bpp.bits() as usize;
+        Self(
+            content
+                .get(offset..)
+                .unwrap_or_default()
+                .chunks_exact(align)
+                .take(count)
+                .filter_map(|c| TileData::from_bpp(c, bpp))
+                .collect(),
+        )
+    }
+
+    // mode 7 character data: 8bpp linear, 1 byte per pixel
+    pub fn from_bytes_mode7(content: &[u8]) -> std::io::Result<Self> {
+        let len = content.len();
+        if len == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file has length 0",
+            ));
+        }
+        if !len.is_multiple_of(64) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file does not align with 64 bytes",
+            ));
+        }
+        if len / 64 > Self::MODE7_MAX {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("tile count exceeds maximum of {} tiles", Self::MODE7_MAX),
+            ));
+        }
+
+        Ok(Self(
+            content
+                .chunks_exact(64)
+                .map(|c| TileData(c.try_into().unwrap()))
+                .collect(),
+        ))
+    }
+
+    pub fn to_bytes_mode7(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|tile| tile.0).collect()
+    }
+
+    pub fn from_reader_mode7(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Self::from_bytes_mode7(&content)
+    }
+
+    pub fn to_writer_mode7(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes_mode7())
+    }
+
+    pub fn from_file_mode7(path: &std::path::PathBuf) -> std::io::Result<Self> {
+        Self::from_bytes_mode7(&std::fs::read(path)?)
+    }
+
+    pub fn write_file_mode7(&self, f: &File) -> std::io::Result<()> {
+        self.to_writer_mode7(f)
+    }
+
+    pub fn to_bytes(&self, bpp: Bpp) -> Vec<u8> {
+        let mut content = Vec::new();
+        for tile in &self.0 {
+            match bpp {
+                Bpp::Two => content.extend(tile.to_2bpp()),
+                Bpp::Four => content.extend(tile.to_4bpp()),
+                Bpp::Eight => content.extend(tile.to_8bpp()),
+            }
         }
+        content
+    }
+
+    // to_bytes drops the bits that don't fit, check for them first when changing bit depth
+    pub fn check_bpp(&self, bpp: Bpp) -> std::io::Result<()> {
+        for (i, tile) in self.0.iter().enumerate() {
+            if let Some(c) = tile.0.iter().find(|&&c| c as u16 >= bpp.to_val()) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("tile {i} uses color {c}, which does not fit in {bpp}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_writer(&self, mut writer: impl Write, bpp: Bpp) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes(bpp))
+    }
+
+    pub fn write_to_file(&self, f: &File, bpp: Bpp) -> std::io::Result<()> {
+        self.to_writer(f, bpp)
     }
 }
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use strum::{EnumIter, EnumString};
+
+use super::{
+    list_items::{BGModeTwo, Bpp, TilemapSize},
+    mode7,
+    palette::Palette,
+    tilemap::Tilemap,
+    tiles::Tileset,
+};
+
+// size of a full VRAM dump
+pub const VRAM_SIZE: usize = 0x10000;
+// size of a full CGRAM dump
+pub const CGRAM_SIZE: usize = 0x200;
+// BGnSC tilemap base is in steps of 1K words
+const TILEMAP_BASE_STEP: usize = 0x400;
+// BGnNBA character base is in steps of 4K words
+const CHR_BASE_STEP: usize = 0x1000;
+
+// background layer of a BG mode, each with a fixed bit depth
+#[derive(EnumString, EnumIter, Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum BGLayer {
+    #[default]
+    M0BG1,
+    M0BG2,
+    M0BG3,
+    M0BG4,
+    M1BG1,
+    M1BG2,
+    M1BG3,
+    M2BG1,
+    M2BG2,
+    M3BG1,
+    M3BG2,
+    M4BG1,
+    M4BG2,
+    M5BG1,
+    M5BG2,
+    M6BG1,
+    M7BG1,
+}
+
+impl fmt::Display for BGLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mode, bg) = self.mode_bg();
+        write!(f, "Mode {mode} BG{bg} ({})", self.bpp())
+    }
+}
+
+impl BGLayer {
+    fn mode_bg(&self) -> (u8, u8) {
+        match self {
+            BGLayer::M0BG1 => (0, 1),
+            BGLayer::M0BG2 => (0, 2),
+            BGLayer::M0BG3 => (0, 3),
+            BGLayer::M0BG4 => (0, 4),
+            BGLayer::M1BG1 => (1, 1),
+            BGLayer::M1BG2 => (1, 2),
+            BGLayer::M1BG3 => (1, 3),
+            BGLayer::M2BG1 => (2, 1),
+            BGLayer::M2BG2 => (2, 2),
+            BGLayer::M3BG1 => (3, 1),
+            BGLayer::M3BG2 => (3, 2),
+            BGLayer::M4BG1 => (4, 1),
+            BGLayer::M4BG2 => (4, 2),
+            BGLayer::M5BG1 => (5, 1),
+            BGLayer::M5BG2 => (5, 2),
+            BGLayer::M6BG1 => (6, 1),
+            BGLayer::M7BG1 => (7, 1),
+        }
+    }
+
+    pub fn bpp(&self) -> Bpp {
+        match self {
+            BGLayer::M0BG1
+            | BGLayer::M0BG2
+            | BGLayer::M0BG3
+            | BGLayer::M0BG4
+            | BGLayer::M1BG3
+            | BGLayer::M4BG2
+            | BGLayer::M5BG2 => Bpp::Two,
+            BGLayer::M1BG1
+            | BGLayer::M1BG2
+            | BGLayer::M2BG1
+            | BGLayer::M2BG2
+            | BGLayer::M3BG2
+            | BGLayer::M5BG1
+            | BGLayer::M6BG1 => Bpp::Four,
+            BGLayer::M3BG1 | BGLayer::M4BG1 | BGLayer::M7BG1 => Bpp::Eight,
+        }
+    }
+
+    // only mode 0 gives each 2bpp layer its own 32 colors
+    pub fn bg_mode_two(&self) -> BGModeTwo {
+        match self {
+            BGLayer::M0BG2 => BGModeTwo::M0BG2,
+            BGLayer::M0BG3 => BGModeTwo::M0BG3,
+            BGLayer::M0BG4 => BGModeTwo::M0BG4,
+            _ => BGModeTwo::M0BG1,
+        }
+    }
+
+    pub fn is_mode7(&self) -> bool {
+        *self == BGLayer::M7BG1
+    }
+}
+
+// where a background layer is stored in VRAM, as set by the BGnSC and BGnNBA registers
+#[derive(Debug, Clone, Copy)]
+pub struct Scene {
+    pub layer: BGLayer,
+    // VRAM word addresses
+    pub tilemap_base: usize,
+    pub chr_base: usize,
+    pub size: TilemapSize,
+}
+
+// len bytes starting at a word address, wrapping around the end of VRAM
+fn read_vram(vram: &[u8], word_addr: usize, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| vram[(word_addr * 2 + i) % VRAM_SIZE])
+        .collect()
+}
+
+impl Scene {
+    pub fn read_files(
+        &self,
+        vram_path: &std::path::PathBuf,
+        cgram_path: &std::path::PathBuf,
+    ) -> std::io::Result<(Palette, Tileset, Tilemap)> {
+        self.read(&std::fs::read(vram_path)?, &std::fs::read(cgram_path)?)
+    }
+
+    pub fn read(&self, vram: &[u8], cgram: &[u8]) -> std::io::Result<(Palette, Tileset, Tilemap)> {
+        if vram.len() != VRAM_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("VRAM dump must be {VRAM_SIZE} bytes"),
+            ));
+        }
+        if cgram.len() != CGRAM_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("CGRAM dump must be {CGRAM_SIZE} bytes"),
+            ));
+        }
+        let palette = Palette::from_bytes_bgr555(cgram);
+
+        // mode 7 data is always at the start of VRAM
+        if self.layer.is_mode7() {
+            let (tilemap, tileset) = mode7::from_bytes(&vram[..mode7::VRAM_SIZE])?;
+            return Ok((palette, tileset, tilemap));
+        }
+
+        for (name, addr, step) in [
+            ("tilemap base", self.tilemap_base, TILEMAP_BASE_STEP),
+            ("character base", self.chr_base, CHR_BASE_STEP),
+        ] {
+            if addr % step != 0 || addr >= VRAM_SIZE / 2 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{name} ${addr:04X} is not a multiple of ${step:X} below $8000"),
+                ));
+            }
+        }
+
+        // tilemap entries can address 1024 tiles
+        let bpp = self.layer.bpp();
+        let chr = read_vram(vram, self.chr_base, Tileset::MAX * 8 * bpp.bits() as usize);
+        let (width, height) = self.size.dims();
+        let map = read_vram(vram, self.tilemap_base, width * height * 2);
+        Ok((
+            palette,
+            Tileset::from_bytes(&chr, bpp)?,
+            Tilemap::from_bytes(&map, self.size)?,
+        ))
+    }
+}
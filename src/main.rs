@@ -1,3 +1,4 @@
+mod cli;
 pub mod data;
 pub mod undo_stack;
 pub mod utils;
@@ -15,6 +16,12 @@ const APP_ID: &str = "com.example.waffle";
 const TILE_W: f64 = 24.0;
 
 fn main() -> glib::ExitCode {
+    // subcommands run headless, anything else is left to the application
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        return code;
+    }
+
     // Register and include resources
     gio::resources_register_include!("waffle.gresource").expect("Failed to register resources.");
 
@@ -39,6 +46,10 @@ fn on_startup(app: &Application) {
     app.set_accels_for_action("debug.printstuff", &[&"<Ctrl>d"]);
     app.set_accels_for_action("win.undo", &[&"<Ctrl>z"]);
     app.set_accels_for_action("win.redo", &[&"<Ctrl>y", &"<Ctrl><Shift>z"]);
+    app.set_accels_for_action("tilemap.cut", &["<Ctrl>x"]);
+    app.set_accels_for_action("tilemap.copy", &["<Ctrl>c"]);
+    app.set_accels_for_action("tilemap.paste", &["<Ctrl>v"]);
+    app.set_accels_for_action("tilemap.selectall", &["<Ctrl>a"]);
 }
 
 fn build_ui(app: &Application) {
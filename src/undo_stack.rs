@@ -3,7 +3,10 @@ use std::collections::VecDeque;
 
 use crate::widgets::window::Window;
 use crate::widgets::{
-    color_picker::operation::ChangePaletteColor, tilemap_editor::operation::ChangeTilemapTile,
+    color_picker::operation::ChangePaletteColor,
+    palette_picker::operation::ChangePaletteRange,
+    tile_editor::operation::ChangeTilePixel,
+    tilemap_editor::operation::{ChangeTilemapCells, ChangeTilemapTile},
 };
 use enum_dispatch::enum_dispatch;
 
@@ -16,7 +19,10 @@ pub trait UndoRedo {
 #[enum_dispatch(UndoRedo)]
 pub enum Operation {
     ChangePaletteColor,
+    ChangePaletteRange,
     ChangeTilemapTile,
+    ChangeTilemapCells,
+    ChangeTilePixel,
 }
 
 #[derive(Default)]
@@ -26,6 +32,7 @@ pub struct UndoStack {
     curr: usize,                  // current position in stack
     palette_dirty: Option<isize>, // number of operations away from a clean copy of palette data
     tilemap_dirty: Option<isize>, // number of operations away from a clean copy of tilemap data
+    tileset_dirty: Option<isize>, // number of operations away from a clean copy of tileset data
 }
 
 impl UndoStack {
@@ -38,6 +45,7 @@ impl UndoStack {
         }
         self.palette_dirty = Some(0);
         self.tilemap_dirty = Some(0);
+        self.tileset_dirty = Some(0);
     }
 
     pub fn clear(&mut self) {
@@ -54,6 +62,9 @@ impl UndoStack {
         if self.tilemap_dirty.is_some_and(|n| n < 0) {
             self.tilemap_dirty = None;
         }
+        if self.tileset_dirty.is_some_and(|n| n < 0) {
+            self.tileset_dirty = None;
+        }
 
         // combine with previous operation, or push directly onto stack
         let palette_dirty = self.palette_dirty();
@@ -66,12 +77,15 @@ impl UndoStack {
             }
             (_, new) => {
                 match new {
-                    Operation::ChangePaletteColor(_) => {
+                    Operation::ChangePaletteColor(_) | Operation::ChangePaletteRange(_) => {
                         self.palette_dirty = self.palette_dirty.map(|n| n + 1)
                     }
-                    Operation::ChangeTilemapTile(_) => {
+                    Operation::ChangeTilemapTile(_) | Operation::ChangeTilemapCells(_) => {
                         self.tilemap_dirty = self.tilemap_dirty.map(|n| n + 1)
                     }
+                    Operation::ChangeTilePixel(_) => {
+                        self.tileset_dirty = self.tileset_dirty.map(|n| n + 1)
+                    }
                 }
                 // limit stack size to Self::MAX
                 if self.stack.len() >= Self::MAX {
@@ -95,12 +109,15 @@ impl UndoStack {
             return;
         };
         match op {
-            Operation::ChangePaletteColor(_) => {
+            Operation::ChangePaletteColor(_) | Operation::ChangePaletteRange(_) => {
                 self.palette_dirty = self.palette_dirty.map(|n| n - 1)
             }
-            Operation::ChangeTilemapTile(_) => {
+            Operation::ChangeTilemapTile(_) | Operation::ChangeTilemapCells(_) => {
                 self.tilemap_dirty = self.tilemap_dirty.map(|n| n - 1)
             }
+            Operation::ChangeTilePixel(_) => {
+                self.tileset_dirty = self.tileset_dirty.map(|n| n - 1)
+            }
         }
         self.curr -= 1;
         op.undo(state);
@@ -117,12 +134,15 @@ impl UndoStack {
             return;
         };
         match op {
-            Operation::ChangePaletteColor(_) => {
+            Operation::ChangePaletteColor(_) | Operation::ChangePaletteRange(_) => {
                 self.palette_dirty = self.palette_dirty.map(|n| n + 1)
             }
-            Operation::ChangeTilemapTile(_) => {
+            Operation::ChangeTilemapTile(_) | Operation::ChangeTilemapCells(_) => {
                 self.tilemap_dirty = self.tilemap_dirty.map(|n| n + 1)
             }
+            Operation::ChangeTilePixel(_) => {
+                self.tileset_dirty = self.tileset_dirty.map(|n| n + 1)
+            }
         }
         self.curr += 1;
         op.redo(state);
@@ -134,6 +154,9 @@ impl UndoStack {
     pub fn mark_palette_clean(&mut self) {
         self.palette_dirty = Some(0);
     }
+    pub fn mark_palette_dirty(&mut self) {
+        self.palette_dirty = None;
+    }
 
     pub fn tilemap_dirty(&self) -> bool {
         !self.tilemap_dirty.is_some_and(|n| n == 0)
@@ -141,4 +164,17 @@ impl UndoStack {
     pub fn mark_tilemap_clean(&mut self) {
         self.tilemap_dirty = Some(0);
     }
+    pub fn mark_tilemap_dirty(&mut self) {
+        self.tilemap_dirty = None;
+    }
+
+    pub fn tileset_dirty(&self) -> bool {
+        self.tileset_dirty.is_none_or(|n| n != 0)
+    }
+    pub fn mark_tileset_clean(&mut self) {
+        self.tileset_dirty = Some(0);
+    }
+    pub fn mark_tileset_dirty(&mut self) {
+        self.tileset_dirty = None;
+    }
 }
@@ -1,14 +1,32 @@
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use gtk::prelude::*;
 use gtk::{
-    ButtonsType, DialogFlags, FileChooserAction, FileChooserDialog, FileFilter, MessageDialog,
-    MessageType, ResponseType, Window,
+    ButtonsType, Dialog, DialogFlags, DropDown, Entry, FileChooserAction, FileChooserDialog,
+    FileFilter, Grid, Label, MessageDialog, MessageType, ResponseType, Window,
+};
+
+use strum::IntoEnumIterator;
+
+use crate::data::{
+    list_items::TilemapSize,
+    rom::AddressKind,
+    source::{identifier, label_from_path, Assembler, CHeader, Source},
+    vram::{BGLayer, Scene},
 };
 
 pub fn file_open_dialog<W: IsA<Window>, F: Fn(PathBuf) + 'static>(parent: W, f: F) {
+    file_open_dialog_titled(parent, "Open File", f);
+}
+
+pub fn file_open_dialog_titled<W: IsA<Window>, F: Fn(PathBuf) + 'static>(
+    parent: W,
+    title: &str,
+    f: F,
+) {
     let dialog = FileChooserDialog::new(
-        Some("Open File"),
+        Some(title),
         Some(&parent),
         FileChooserAction::Open,
         &[("Cancel", ResponseType::Cancel), ("Open", ResponseType::Ok)],
@@ -102,3 +120,352 @@ pub fn save_changes_dialog<W: IsA<Window>>(
 
     dialog.show();
 }
+
+fn png_filter() -> FileFilter {
+    let png_filter = FileFilter::new();
+    png_filter.set_name(Some("PNG Images (.png)"));
+    png_filter.add_mime_type("image/png");
+    png_filter.add_suffix("png");
+    png_filter
+}
+
+pub fn png_open_dialog<W: IsA<Window>, F: Fn(PathBuf) + 'static>(parent: W, f: F) {
+    let dialog = FileChooserDialog::new(
+        Some("Open Image"),
+        Some(&parent),
+        FileChooserAction::Open,
+        &[("Cancel", ResponseType::Cancel), ("Open", ResponseType::Ok)],
+    );
+    dialog.add_filter(&png_filter());
+
+    dialog.connect_response(move |d: &FileChooserDialog, response: ResponseType| {
+        if response == ResponseType::Ok {
+            let file = d.file().expect("Couldn't get file");
+            let filename = file.path().expect("Couldn't get file path");
+            f(filename);
+        }
+
+        d.close();
+    });
+
+    dialog.show();
+}
+
+// (id, label, [(option id, option label)]), read back with d.choice(id)
+pub type FileChoice<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+pub fn png_save_dialog<W: IsA<Window>, F: Fn(FileChooserDialog, PathBuf) + 'static>(
+    parent: &W,
+    choices: &[FileChoice],
+    f: F,
+) {
+    export_dialog(parent, "Export Image", &png_filter(), choices, f);
+}
+
+// save as assembler source, options are chosen in the dialog
+pub fn source_save_dialog<W: IsA<Window>, F: Fn(Source, PathBuf) + 'static>(parent: &W, f: F) {
+    let source_filter = FileFilter::new();
+    source_filter.set_name(Some("Assembler Source (.asm, .s, .inc)"));
+    source_filter.add_suffix("asm");
+    source_filter.add_suffix("s");
+    source_filter.add_suffix("inc");
+
+    let assemblers: Vec<(String, String)> = Assembler::iter()
+        .map(|a| (format!("{a:?}"), a.to_string()))
+        .collect();
+    let assemblers: Vec<(&str, &str)> = assemblers
+        .iter()
+        .map(|(id, label)| (id.as_str(), label.as_str()))
+        .collect();
+
+    export_dialog(
+        parent,
+        "Export Source",
+        &source_filter,
+        &[
+            ("assembler", "Assembler", &assemblers),
+            ("comments", "Comments", &[]),
+        ],
+        move |d, filepath| {
+            let asm = d
+                .choice("assembler")
+                .and_then(|a| Assembler::from_str(&a).ok())
+                .unwrap_or_default();
+            let source = Source {
+                asm,
+                label: label_from_path(&filepath),
+                comments: d.choice("comments").is_some_and(|c| c == "true"),
+            };
+            f(source, filepath);
+        },
+    );
+}
+
+// save as a C header, then ask for the symbol name
+pub fn c_header_save_dialog<W: IsA<Window>, F: Fn(CHeader, PathBuf) + Clone + 'static>(
+    parent: &W,
+    f: F,
+) {
+    let header_filter = FileFilter::new();
+    header_filter.set_name(Some("C Header (.h)"));
+    header_filter.add_suffix("h");
+
+    let parent = parent.clone().upcast::<Window>();
+    export_dialog(
+        &parent.clone(),
+        "Export C Header",
+        &header_filter,
+        &[("comments", "Comments", &[])],
+        move |d, filepath| {
+            let comments = d.choice("comments").is_some_and(|c| c == "true");
+            let f = f.clone();
+            text_dialog(
+                &parent,
+                "Symbol Name",
+                "Array name",
+                &label_from_path(&filepath),
+                move |name| {
+                    let header = CHeader {
+                        name: identifier(&name),
+                        comments,
+                    };
+                    f(header, filepath.clone());
+                },
+            );
+        },
+    );
+}
+
+// save dialog with a file filter and extra choices, choices without options are checkboxes
+fn export_dialog<W: IsA<Window>, F: Fn(FileChooserDialog, PathBuf) + 'static>(
+    parent: &W,
+    title: &str,
+    filter: &FileFilter,
+    choices: &[FileChoice],
+    f: F,
+) {
+    let dialog = FileChooserDialog::new(
+        Some(title),
+        Some(parent),
+        FileChooserAction::Save,
+        &[
+            ("Cancel", ResponseType::Cancel),
+            ("Export", ResponseType::Accept),
+        ],
+    );
+    dialog.add_filter(filter);
+    for (id, label, options) in choices {
+        dialog.add_choice(*id, *label, options);
+        match options.first() {
+            Some((first, _)) => dialog.set_choice(id, first),
+            None => dialog.set_choice(id, "true"),
+        }
+    }
+
+    dialog.connect_response(move |d: &FileChooserDialog, response: ResponseType| {
+        if response == ResponseType::Accept {
+            let file = d.file().expect("Couldn't get file");
+            let filename = file.path().expect("Couldn't get file path");
+            f(d.clone(), filename);
+        }
+
+        d.close();
+    });
+
+    dialog.show();
+}
+
+// "$1234" and "0x1234" are hex, "1234" is decimal unless hex is true
+fn parse_number(s: &str, hex: bool) -> Option<usize> {
+    let s = s.trim().replace('_', "");
+    if let Some(h) = s.strip_prefix('$').or(s.strip_prefix("0x")) {
+        usize::from_str_radix(h, 16).ok()
+    } else if hex {
+        usize::from_str_radix(&s, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+// ask for where data is inside a rom file: address kind, address, size in bytes
+pub fn rom_address_dialog<W: IsA<Window>, F: Fn(AddressKind, usize, usize) + 'static>(
+    parent: &W,
+    default_len: usize,
+    f: F,
+) {
+    let dialog = Dialog::with_buttons(
+        Some("Open from ROM"),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[("Cancel", ResponseType::Cancel), ("Open", ResponseType::Ok)],
+    );
+
+    let kinds: Vec<String> = AddressKind::iter().map(|k| k.to_string()).collect();
+    let kind_select = DropDown::from_strings(&kinds.iter().map(|k| k.as_str()).collect::<Vec<_>>());
+    let addr_entry = Entry::builder()
+        .placeholder_text("hex, e.g. 1C8000")
+        .build();
+    let len_entry = Entry::builder().text(default_len.to_string()).build();
+
+    let grid = Grid::builder()
+        .row_spacing(6)
+        .column_spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    grid.attach(&Label::new(Some("Address type")), 0, 0, 1, 1);
+    grid.attach(&kind_select, 1, 0, 1, 1);
+    grid.attach(&Label::new(Some("Address")), 0, 1, 1, 1);
+    grid.attach(&addr_entry, 1, 1, 1, 1);
+    grid.attach(&Label::new(Some("Size (uncompressed only)")), 0, 2, 1, 1);
+    grid.attach(&len_entry, 1, 2, 1, 1);
+    dialog.content_area().append(&grid);
+
+    dialog.connect_response(move |d: &Dialog, response: ResponseType| {
+        if response == ResponseType::Ok {
+            let kind = AddressKind::iter()
+                .nth(kind_select.selected() as usize)
+                .unwrap_or_default();
+            match (
+                parse_number(&addr_entry.text(), true),
+                parse_number(&len_entry.text(), false),
+            ) {
+                (Some(addr), Some(len)) => f(kind, addr, len),
+                _ => eprintln!("Error: invalid address or size"),
+            }
+        }
+
+        d.close();
+    });
+
+    dialog.show();
+}
+
+// ask for the BG layer and the BGnSC/BGnNBA settings of a scene in a VRAM dump
+pub fn scene_dialog<W: IsA<Window>, F: Fn(Scene) + 'static>(parent: &W, f: F) {
+    let dialog = Dialog::with_buttons(
+        Some("Load Scene"),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[("Cancel", ResponseType::Cancel), ("Open", ResponseType::Ok)],
+    );
+
+    let layers: Vec<String> = BGLayer::iter().map(|l| l.to_string()).collect();
+    let layer_select =
+        DropDown::from_strings(&layers.iter().map(|l| l.as_str()).collect::<Vec<_>>());
+    let tilemap_entry = Entry::builder().text("0000").build();
+    let sizes: Vec<String> = TilemapSize::iter().map(|s| s.to_string()).collect();
+    let size_select = DropDown::from_strings(&sizes.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+    let chr_entry = Entry::builder().text("0000").build();
+
+    let grid = Grid::builder()
+        .row_spacing(6)
+        .column_spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    grid.attach(&Label::new(Some("BG layer")), 0, 0, 1, 1);
+    grid.attach(&layer_select, 1, 0, 1, 1);
+    grid.attach(&Label::new(Some("Tilemap base (word address)")), 0, 1, 1, 1);
+    grid.attach(&tilemap_entry, 1, 1, 1, 1);
+    grid.attach(&Label::new(Some("Tilemap size")), 0, 2, 1, 1);
+    grid.attach(&size_select, 1, 2, 1, 1);
+    grid.attach(
+        &Label::new(Some("Character base (word address)")),
+        0,
+        3,
+        1,
+        1,
+    );
+    grid.attach(&chr_entry, 1, 3, 1, 1);
+    dialog.content_area().append(&grid);
+
+    dialog.connect_response(move |d: &Dialog, response: ResponseType| {
+        if response == ResponseType::Ok {
+            let layer = BGLayer::iter()
+                .nth(layer_select.selected() as usize)
+                .unwrap_or_default();
+            let size = TilemapSize::iter()
+                .nth(size_select.selected() as usize)
+                .unwrap_or_default();
+            match (
+                parse_number(&tilemap_entry.text(), true),
+                parse_number(&chr_entry.text(), true),
+            ) {
+                (Some(tilemap_base), Some(chr_base)) => f(Scene {
+                    layer,
+                    tilemap_base,
+                    chr_base,
+                    size,
+                }),
+                _ => eprintln!("Error: invalid base address"),
+            }
+        }
+
+        d.close();
+    });
+
+    dialog.show();
+}
+
+// ask for a single line of text
+pub fn text_dialog<W: IsA<Window>, F: Fn(String) + 'static>(
+    parent: &W,
+    title: &str,
+    label: &str,
+    default: &str,
+    f: F,
+) {
+    let dialog = Dialog::with_buttons(
+        Some(title),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[("Cancel", ResponseType::Cancel), ("OK", ResponseType::Ok)],
+    );
+
+    let entry = Entry::builder().text(default).build();
+    let grid = Grid::builder()
+        .column_spacing(6)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    grid.attach(&Label::new(Some(label)), 0, 0, 1, 1);
+    grid.attach(&entry, 1, 0, 1, 1);
+    dialog.content_area().append(&grid);
+
+    dialog.connect_response(move |d: &Dialog, response: ResponseType| {
+        if response == ResponseType::Ok {
+            f(entry.text().to_string());
+        }
+
+        d.close();
+    });
+
+    dialog.show();
+}
+
+// ask for a single number, decimal or hex with a "$" or "0x" prefix
+pub fn number_dialog<W: IsA<Window>, F: Fn(usize) + 'static>(
+    parent: &W,
+    title: &str,
+    label: &str,
+    default: usize,
+    f: F,
+) {
+    text_dialog(
+        parent,
+        title,
+        label,
+        &default.to_string(),
+        move |text| match parse_number(&text, false) {
+            Some(n) => f(n),
+            None => eprintln!("Error: invalid number"),
+        },
+    );
+}
@@ -1,5 +1,6 @@
 pub mod color_picker;
 pub mod palette_picker;
+pub mod tile_editor;
 pub mod tilemap_editor;
 pub mod tile_picker;
 pub mod window;
@@ -1,11 +1,12 @@
 mod imp;
+pub mod operation;
 pub mod utils;
 
 use std::str::FromStr;
 
 use std::path::PathBuf;
 
-use gio::{ActionEntry, SimpleActionGroup};
+use gio::{ActionEntry, PropertyAction, SimpleActionGroup};
 use glib::clone;
 use gtk::GestureClick;
 use gtk::{gio, glib};
@@ -131,6 +132,7 @@ impl PalettePicker {
                         let is_transparent = match tile_bpp {
                             Bpp::Two => j % 4 == 0,
                             Bpp::Four => j == 0,
+                            Bpp::Eight => i == 0 && j == 0,
                         };
                         if is_transparent {
                             cr.arc(
@@ -152,7 +154,10 @@ impl PalettePicker {
                 let x_offset = 0.0;
                 let y_offset = (state.palette_base() / 16) as f64 * TILE_W;
                 let width = 16.0 * TILE_W;
-                let height = (tile_bpp.to_val() * 8 / 16) as f64 * TILE_W;
+                let height = match tile_bpp {
+                    Bpp::Eight => 16.0 * TILE_W,
+                    _ => (tile_bpp.to_val() * 8 / 16) as f64 * TILE_W,
+                };
                 cr.rectangle(x_offset, y_offset, width, height);
                 cr.rectangle(0.0, 0.0, x as f64, y as f64);
                 let _ = cr.save();
@@ -162,15 +167,14 @@ impl PalettePicker {
                 let _ = cr.restore();
 
                 // draw current palette group outline
-                let pal_start_idx = sel_idx - (sel_idx % tile_bpp.to_val());
+                let pal_start_idx = state.curr_palette_start();
                 let x_offset = (pal_start_idx % 16) as f64 * TILE_W;
                 let y_offset = (pal_start_idx / 16) as f64 * TILE_W;
-                cr.rectangle(
-                    x_offset,
-                    y_offset,
-                    TILE_W * tile_bpp.to_val() as f64,
-                    TILE_W,
-                );
+                let (width, height) = match tile_bpp {
+                    Bpp::Eight => (16.0 * TILE_W, 16.0 * TILE_W),
+                    _ => (TILE_W * tile_bpp.to_val() as f64, TILE_W),
+                };
+                cr.rectangle(x_offset, y_offset, width, height);
 
                 cr.clip_preserve();
                 cr.set_line_width(2.0);
@@ -218,18 +222,70 @@ impl PalettePicker {
                                     #[weak]
                                     state,
                                     move || {
-                                        open_file(&state, filepath.clone(), file_format);
+                                        if let Err(e) =
+                                            open_file(&state, filepath.clone(), file_format)
+                                        {
+                                            eprintln!("Error: {e}");
+                                        }
                                     }
                                 ),
                             )
-                        } else {
-                            open_file(&state, filepath.clone(), file_format);
+                        } else if let Err(e) = open_file(&state, filepath.clone(), file_format) {
+                            eprintln!("Error: {e}");
                         }
                     });
                 }
             ))
             .build();
 
+        let action_open_rom = ActionEntry::builder("openrom")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog(state.clone(), move |filepath| {
+                        // 256 colors, 2 bytes each
+                        rom_address_dialog(
+                            &state.clone(),
+                            512,
+                            clone!(
+                                #[weak]
+                                state,
+                                move |kind, addr, len| {
+                                    let filepath = filepath.clone();
+                                    // check for unsaved data
+                                    if state.palette_dirty() {
+                                        unsaved_palette_dialog(
+                                            &state,
+                                            clone!(
+                                                #[weak]
+                                                state,
+                                                move || {
+                                                    if let Err(e) = open_rom(
+                                                        &state,
+                                                        filepath.clone(),
+                                                        kind,
+                                                        addr,
+                                                        len,
+                                                    ) {
+                                                        eprintln!("Error: {e}");
+                                                    }
+                                                }
+                                            ),
+                                        )
+                                    } else if let Err(e) =
+                                        open_rom(&state, filepath, kind, addr, len)
+                                    {
+                                        eprintln!("Error: {e}");
+                                    }
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+
         let action_reload = ActionEntry::builder("reload")
             .activate(clone!(
                 #[weak]
@@ -241,19 +297,23 @@ impl PalettePicker {
                         return;
                     };
 
+                    let reload = clone!(
+                        #[weak]
+                        state,
+                        move || {
+                            if let Some(region) = state.palette_rom().filter(|r| r.path == file) {
+                                reload_rom(&state, region);
+                            } else if let Err(e) =
+                                open_file(&state, file.clone(), state.palette_format())
+                            {
+                                eprintln!("Error: {e}");
+                            }
+                        }
+                    );
                     if state.palette_dirty() {
-                        unsaved_palette_dialog(
-                            &state,
-                            clone!(
-                                #[weak]
-                                state,
-                                move || {
-                                    open_file(&state, file.clone(), PaletteFile::BGR555);
-                                }
-                            ),
-                        );
+                        unsaved_palette_dialog(&state, reload);
                     } else {
-                        open_file(&state, file.clone(), PaletteFile::BGR555);
+                        reload();
                     }
                 }
             ))
@@ -267,7 +327,9 @@ impl PalettePicker {
                     let Some(filepath) = state.palette_file() else {
                         return;
                     };
-                    save_file(&state, filepath, PaletteFile::default());
+                    if let Err(e) = save_file(&state, filepath, state.palette_format()) {
+                        eprintln!("Error saving file: {e}");
+                    }
                 }
             ))
             .build();
@@ -287,14 +349,87 @@ impl PalettePicker {
                         PaletteFile::from_str(&file_format).expect("invalid file format");
 
                     file_save_dialog(&state.clone(), move |_, filepath| {
-                        save_file(&state, filepath, file_format);
+                        if let Err(e) = save_file(&state, filepath, file_format) {
+                            eprintln!("Error saving file: {e}");
+                        }
+                    });
+                }
+            ))
+            .build();
+
+        let action_export_header = ActionEntry::builder("exportheader")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    c_header_save_dialog(
+                        &state.clone(),
+                        clone!(
+                            #[weak]
+                            state,
+                            move |header, filepath| {
+                                export_header(&state, header, filepath);
+                            }
+                        ),
+                    );
+                }
+            ))
+            .build();
+
+        let action_export_source = ActionEntry::builder("exportsource")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    source_save_dialog(&state.clone(), move |source, filepath| {
+                        export_source(&state, source, filepath);
+                    });
+                }
+            ))
+            .build();
+
+        let action_import_row = ActionEntry::builder("importrow")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog(state.clone(), move |filepath| {
+                        import_row(&state, filepath);
+                    });
+                }
+            ))
+            .build();
+
+        let action_export_row = ActionEntry::builder("exportrow")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_save_dialog(&state.clone(), move |_, filepath| {
+                        export_row(&state, filepath);
                     });
                 }
             ))
             .build();
 
         let actions = SimpleActionGroup::new();
-        actions.add_action_entries([action_open, action_reload, action_save, action_save_as]);
+        actions.add_action_entries([
+            action_open,
+            action_open_rom,
+            action_reload,
+            action_save,
+            action_save_as,
+            action_import_row,
+            action_export_row,
+            action_export_source,
+            action_export_header,
+        ]);
+        // compression used when opening and saving
+        actions.add_action(&PropertyAction::new(
+            "compression",
+            state,
+            "palette_compression",
+        ));
 
         // enable/disable actions
         let reload = actions.lookup_action("reload").unwrap();
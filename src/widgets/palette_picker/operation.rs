@@ -0,0 +1,40 @@
+use crate::data::color::Color;
+use crate::undo_stack::UndoRedo;
+use crate::widgets::window::Window;
+
+pub struct ChangePaletteRange {
+    start: u8,
+    before: Vec<Color>,
+    after: Vec<Color>,
+}
+
+impl UndoRedo for ChangePaletteRange {
+    fn undo(&self, state: &Window) {
+        state.set_palette_sel_idx(self.start);
+        self.write(state, &self.before);
+    }
+
+    fn redo(&self, state: &Window) {
+        state.set_palette_sel_idx(self.start);
+        self.write(state, &self.after);
+    }
+}
+
+impl ChangePaletteRange {
+    pub fn new(start: u8, before: Vec<Color>, after: Vec<Color>) -> Self {
+        Self {
+            start,
+            before,
+            after,
+        }
+    }
+
+    fn write(&self, state: &Window, colors: &[Color]) {
+        state.modify_palette_data(|palette| {
+            for (c, new_c) in palette.0[self.start as usize..].iter_mut().zip(colors) {
+                *c = *new_c;
+            }
+            true
+        });
+    }
+}
@@ -3,26 +3,60 @@ use std::path::PathBuf;
 
 use gtk::glib::{self, clone};
 
-use crate::data::{file_format::PaletteFile, palette::Palette};
+use crate::data::{
+    file_format::PaletteFile,
+    palette::Palette,
+    rom::{AddressKind, RomRegion},
+    source::{CHeader, Source},
+};
+use crate::undo_stack::UndoRedo;
 use crate::utils::*;
-use crate::widgets::window::Window;
+use crate::widgets::{palette_picker::operation::ChangePaletteRange, window::Window};
 
-pub fn open_file(state: &Window, filepath: PathBuf, file_format: PaletteFile) {
-    let file_result = match file_format {
-        PaletteFile::BGR555 => Palette::from_file_bgr555(&filepath),
-        PaletteFile::RGB24 => Palette::from_file_rgb24(&filepath),
-    };
-    match file_result {
-        Ok(res) => {
-            println!("load palette: {filepath:?}");
-            state.set_palette_data(res);
-            // only store file name (and allow reloading)
-            // if the type is BGR555
-            if file_format == PaletteFile::default() {
-                state.set_palette_file(Some(filepath));
-            } else {
-                state.set_palette_file(None::<PathBuf>);
-            }
+pub fn open_file(
+    state: &Window,
+    filepath: PathBuf,
+    file_format: PaletteFile,
+) -> std::io::Result<()> {
+    let res = Palette::from_file_format(&filepath, file_format, state.palette_compression())?;
+    println!("load palette: {filepath:?}");
+    state.set_palette_data(res);
+    state.set_palette_file(Some(filepath));
+    state.set_palette_format(file_format);
+    state.set_palette_rom(None);
+    state.mark_palette_clean();
+    state.clear_history();
+    Ok(())
+}
+
+// BGR555 only
+pub fn open_rom(
+    state: &Window,
+    filepath: PathBuf,
+    kind: AddressKind,
+    addr: usize,
+    len: usize,
+) -> std::io::Result<()> {
+    let (region, content) =
+        RomRegion::read(&filepath, kind, addr, len, state.palette_compression())?;
+    println!(
+        "load palette from rom: {filepath:?} at {:#X}",
+        region.offset
+    );
+    state.set_palette_data(Palette::from_bytes_bgr555(&content));
+    state.set_palette_file(Some(filepath));
+    state.set_palette_format(PaletteFile::BGR555);
+    state.set_palette_rom(Some(region));
+    state.mark_palette_clean();
+    state.clear_history();
+    Ok(())
+}
+
+pub fn reload_rom(state: &Window, region: RomRegion) {
+    match region.reload(state.palette_compression()) {
+        Ok(content) => {
+            println!("reload palette from rom: {:?}", region.path);
+            state.set_palette_data(Palette::from_bytes_bgr555(&content));
             state.mark_palette_clean();
             state.clear_history();
         }
@@ -30,26 +64,36 @@ pub fn open_file(state: &Window, filepath: PathBuf, file_format: PaletteFile) {
     }
 }
 
-pub fn save_file(state: &Window, filepath: PathBuf, file_format: PaletteFile) {
-    match File::create(filepath.clone()) {
-        Ok(f) => {
-            match file_format {
-                PaletteFile::BGR555 => {
-                    let _ = state.palette_data().write_file_bgr555(&f);
-                }
-                PaletteFile::RGB24 => {
-                    let _ = state.palette_data().write_file_rgb24(&f);
-                }
-            }
-            println!("save palette: {filepath:?}");
-
-            if file_format == PaletteFile::default() {
-                state.set_palette_file(Some(filepath));
-                state.mark_palette_clean();
-            }
+pub fn save_file(
+    state: &Window,
+    filepath: PathBuf,
+    file_format: PaletteFile,
+) -> std::io::Result<()> {
+    // write back into the rom it was opened from
+    if let Some(region) = state.palette_rom().filter(|r| r.path == filepath) {
+        // the rom can only be patched, never replaced by a palette file
+        if file_format != PaletteFile::BGR555 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("can't save a {file_format} palette into a rom"),
+            ));
         }
-        Err(e) => eprintln!("Error saving file: {e}"),
+        let content = state.palette_data().to_bytes_bgr555();
+        region.patch(&content, state.palette_compression())?;
+        println!("patch palette: {filepath:?} at {:#X}", region.offset);
+        state.mark_palette_clean();
+        return Ok(());
     }
+
+    let f = File::create(filepath.clone())?;
+    state
+        .palette_data()
+        .write_file_format(&f, file_format, state.palette_compression())?;
+    println!("save palette: {filepath:?}");
+    state.set_palette_file(Some(filepath));
+    state.set_palette_format(file_format);
+    state.mark_palette_clean();
+    Ok(())
 }
 
 pub fn unsaved_palette_dialog(state: &Window, after: impl Fn() + Clone + 'static) {
@@ -70,13 +114,17 @@ pub fn unsaved_palette_dialog(state: &Window, after: impl Fn() + Clone + 'static
                 let after1 = after.clone();
                 if let Some(filepath) = state.palette_file() {
                     // save to palette_file
-                    save_file(&state, filepath.clone(), PaletteFile::BGR555);
-                    after1();
+                    match save_file(&state, filepath.clone(), state.palette_format()) {
+                        Ok(_) => after1(),
+                        Err(e) => eprintln!("Error saving file: {e}"),
+                    }
                 } else {
                     // save to new file
                     file_save_dialog(&state.clone(), move |_, filepath| {
-                        save_file(&state, filepath, PaletteFile::BGR555);
-                        after1();
+                        match save_file(&state, filepath, state.palette_format()) {
+                            Ok(_) => after1(),
+                            Err(e) => eprintln!("Error saving file: {e}"),
+                        }
                     });
                 }
             }
@@ -92,3 +140,53 @@ pub fn unsaved_palette_dialog(state: &Window, after: impl Fn() + Clone + 'static
         ),
     );
 }
+
+// write BGR555 colors starting at the selected subpalette
+pub fn import_row(state: &Window, filepath: PathBuf) {
+    let mut after = match Palette::read_colors_bgr555(&filepath) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return;
+        }
+    };
+    let start = state.curr_palette_start() as usize;
+    if start + after.len() > 256 {
+        eprintln!("colors past the end of the palette are dropped");
+        after.truncate(256 - start);
+    }
+    println!("import {} colors at {start}: {filepath:?}", after.len());
+
+    let before = state.palette_data().0[start..start + after.len()].to_vec();
+    let op = ChangePaletteRange::new(start as u8, before, after);
+    op.redo(state);
+    state.push_op(op.into());
+}
+
+// write the selected subpalette only
+pub fn export_row(state: &Window, filepath: PathBuf) {
+    let start = state.curr_palette_start() as usize;
+    let colors = &state.palette_data().0[start..start + state.tile_bpp().to_val() as usize];
+    match File::create(filepath.clone()).and_then(|f| Palette::write_colors_bgr555(colors, &f)) {
+        Ok(_) => println!("export palette row: {filepath:?}"),
+        Err(e) => eprintln!("Error saving file: {e}"),
+    }
+}
+
+pub fn export_source(state: &Window, source: Source, filepath: PathBuf) {
+    match File::create(filepath.clone())
+        .and_then(|f| source.write_palette(&state.palette_data(), &f))
+    {
+        Ok(_) => println!("export palette source: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting source: {e}"),
+    }
+}
+
+pub fn export_header(state: &Window, header: CHeader, filepath: PathBuf) {
+    match File::create(filepath.clone())
+        .and_then(|f| header.write_palette(&state.palette_data(), &f))
+    {
+        Ok(_) => println!("export palette header: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting header: {e}"),
+    }
+}
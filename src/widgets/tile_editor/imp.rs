@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+
+use glib::subclass::InitializingObject;
+use gtk::glib;
+use gtk::subclass::prelude::*;
+use gtk::{CompositeTemplate, DrawingArea, ToggleButton};
+
+use crate::data::list_items::DrawMode;
+
+#[derive(CompositeTemplate, Default)]
+#[template(resource = "/com/example/waffle/tile_editor.ui")]
+pub struct TileEditor {
+    #[template_child]
+    pub tile_edit_drawing: TemplateChild<DrawingArea>,
+    #[template_child]
+    pub pencil_btn: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub line_btn: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub fill_btn: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub pick_btn: TemplateChild<ToggleButton>,
+
+    pub curr_drag: RefCell<DrawMode>,
+}
+
+// The central trait for subclassing a GObject
+#[glib::object_subclass]
+impl ObjectSubclass for TileEditor {
+    const NAME: &'static str = "TileEditor";
+    type Type = super::TileEditor;
+    type ParentType = gtk::Box;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for TileEditor {}
+impl WidgetImpl for TileEditor {}
+impl BoxImpl for TileEditor {}
@@ -0,0 +1,315 @@
+mod imp;
+pub mod operation;
+
+use std::collections::{HashMap, HashSet};
+
+use glib::clone;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::GestureDrag;
+
+use self::operation::ChangeTilePixel;
+
+use crate::data::{
+    list_items::{DrawMode, TileSize},
+    render::render_tiles,
+};
+use crate::widgets::window::{draw::paint_image, Window};
+
+glib::wrapper! {
+    pub struct TileEditor(ObjectSubclass<imp::TileEditor>)
+        @extends gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget,
+                    gtk::Orientable;
+}
+
+impl TileEditor {
+    pub fn handle_action(&self, state: &Window) {
+        // click/drag on editor
+        self.setup_gesture(state);
+    }
+
+    pub fn render_widget(&self, state: &Window) {
+        state.connect_tileset_data_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_| {
+                this.imp().tile_edit_drawing.queue_draw();
+            }
+        ));
+
+        state.connect_tileset_sel_idx_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_| {
+                this.imp().tile_edit_drawing.queue_draw();
+            }
+        ));
+
+        state.connect_palette_data_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_| {
+                this.imp().tile_edit_drawing.queue_draw();
+            }
+        ));
+
+        state.connect_palette_sel_idx_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_| {
+                this.imp().tile_edit_drawing.queue_draw();
+            }
+        ));
+
+        state.connect_tile_size_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_| {
+                this.imp().tile_edit_drawing.queue_draw();
+            }
+        ));
+
+        self.imp().tile_edit_drawing.set_draw_func(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            state,
+            move |_, cr, w, _| {
+                let _ = cr.save();
+                cr.set_antialias(gtk::cairo::Antialias::None);
+                this.draw_tile(cr, &state, w as f64);
+                let _ = cr.restore();
+            }
+        ));
+    }
+
+    fn setup_gesture(&self, state: &Window) {
+        let drag_event = GestureDrag::new();
+        drag_event.connect_drag_begin(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            state,
+            move |_, x, y| {
+                let imp = this.imp();
+
+                // calculate pixel position
+                let Some(px) = this.cursor_to_px(&state, x, y) else {
+                    return;
+                };
+
+                if imp.pencil_btn.is_active() {
+                    let mut set = HashSet::new();
+                    set.insert(px);
+                    imp.curr_drag.replace(DrawMode::Pen(set));
+                    imp.tile_edit_drawing.queue_draw();
+                } else if imp.line_btn.is_active() {
+                    imp.curr_drag.replace(DrawMode::Line { start: px, end: px });
+                    imp.tile_edit_drawing.queue_draw();
+                } else if imp.fill_btn.is_active() {
+                    let region = this.fill_region(&state, px);
+                    this.put_pixels(&state, &region);
+                } else if imp.pick_btn.is_active() {
+                    let Some(c) = this.get_pixel(&state, px) else {
+                        return;
+                    };
+                    state.set_palette_sel_idx(state.curr_palette_start() + c);
+                } else {
+                    eprintln!("draw mode not selected");
+                }
+            }
+        ));
+        drag_event.connect_drag_update(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            state,
+            move |drag, dx, dy| {
+                let imp = this.imp();
+
+                // calculate pixel position
+                let Some((x, y)) = drag.start_point() else {
+                    return;
+                };
+                let Some(px) = this.cursor_to_px(&state, x + dx, y + dy) else {
+                    return;
+                };
+
+                let changed = match &mut *imp.curr_drag.borrow_mut() {
+                    DrawMode::Pen(set) => set.insert(px),
+                    DrawMode::Line { start: _, end } => {
+                        let changed = *end != px;
+                        *end = px;
+                        changed
+                    }
+                    _ => false,
+                };
+                if changed {
+                    imp.tile_edit_drawing.queue_draw();
+                }
+            }
+        ));
+        drag_event.connect_drag_end(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            state,
+            move |_, _, _| {
+                let imp = this.imp();
+
+                let pixels: Vec<_> = match *imp.curr_drag.borrow() {
+                    DrawMode::Pen(ref set) => set.iter().copied().collect(),
+                    DrawMode::Line { start, end } => DrawMode::line_points(start, end),
+                    _ => Vec::new(),
+                };
+                imp.curr_drag.replace(DrawMode::None);
+
+                if !pixels.is_empty() {
+                    this.put_pixels(&state, &pixels);
+                }
+                imp.tile_edit_drawing.queue_draw();
+            }
+        ));
+        self.imp().tile_edit_drawing.add_controller(drag_event);
+    }
+
+    // width of the edited area in pixels
+    fn side_len(state: &Window) -> usize {
+        match state.tile_size() {
+            TileSize::Eight => 8,
+            TileSize::Sixteen => 16,
+        }
+    }
+
+    // (tile index, pixel index) of a pixel in the edited area
+    fn px_to_idx(state: &Window, (x, y): (usize, usize)) -> (usize, usize) {
+        let tile_idx = state.tileset_sel_idx() as usize + x / 8 + (y / 8) * 16;
+        (tile_idx, (y % 8) * 8 + x % 8)
+    }
+
+    fn cursor_to_px(&self, state: &Window, x: f64, y: f64) -> Option<(usize, usize)> {
+        let side_len = Self::side_len(state);
+        let pxl_w = self.imp().tile_edit_drawing.width() as f64 / side_len as f64;
+
+        let (px_x, px_y) = (x / pxl_w, y / pxl_w);
+        if px_x < 0.0 || px_y < 0.0 || px_x >= side_len as f64 || px_y >= side_len as f64 {
+            // cursor position outside of tile
+            return None;
+        }
+        Some((px_x.floor() as usize, px_y.floor() as usize))
+    }
+
+    fn get_pixel(&self, state: &Window, px: (usize, usize)) -> Option<u8> {
+        let (t, p) = Self::px_to_idx(state, px);
+        state.tileset_data().0.get(t).map(|tile| tile.0[p])
+    }
+
+    // contiguous pixels with the same color index as px
+    fn fill_region(&self, state: &Window, px: (usize, usize)) -> Vec<(usize, usize)> {
+        let side_len = Self::side_len(state);
+        let Some(target) = self.get_pixel(state, px) else {
+            return Vec::new();
+        };
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![px];
+        while let Some((x, y)) = stack.pop() {
+            if self.get_pixel(state, (x, y)) != Some(target) || !visited.insert((x, y)) {
+                continue;
+            }
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if x + 1 < side_len {
+                stack.push((x + 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+            if y + 1 < side_len {
+                stack.push((x, y + 1));
+            }
+        }
+        visited.into_iter().collect()
+    }
+
+    // set pixels to the color selected in palette picker
+    fn put_pixels(&self, state: &Window, pixels: &[(usize, usize)]) {
+        let color = state.palette_sel_idx() - state.curr_palette_start();
+        let targets: Vec<_> = pixels.iter().map(|px| Self::px_to_idx(state, *px)).collect();
+        state.modify_tileset_data(|tileset| {
+            let mut map = HashMap::new();
+            for (t, p) in &targets {
+                // pixels outside of tileset can't be edited
+                let Some(tile) = tileset.0.get_mut(*t) else {
+                    continue;
+                };
+                if tile.0[*p] != color {
+                    map.insert((*t, *p), tile.0[*p]);
+                    tile.0[*p] = color;
+                }
+            }
+            if !map.is_empty() {
+                state.push_op(ChangeTilePixel::new(map, color).into());
+                true
+            } else {
+                // nothing changed
+                false
+            }
+        });
+    }
+
+    fn draw_tile(&self, cr: &gtk::cairo::Context, state: &Window, width: f64) {
+        let tileset = state.tileset_data();
+        let side_len = Self::side_len(state);
+        let pxl_w = width / side_len as f64;
+
+        // fallback color
+        cr.set_source_rgb(0.4, 0.4, 0.4);
+        let _ = cr.paint();
+
+        // draw tile(s) scaled up to fill the editor
+        let sel_idx = state.tileset_sel_idx() as usize;
+        let tiles_w = side_len / 8;
+        let indices: Vec<usize> = (0..tiles_w * tiles_w)
+            .map(|i| sel_idx + (i / tiles_w) * 16 + i % tiles_w)
+            .collect();
+        let bpp = state.tile_bpp();
+        let image = render_tiles(
+            &tileset,
+            &state.palette_data(),
+            &indices,
+            tiles_w,
+            state.palette_start(bpp),
+            state.render_options(bpp),
+        );
+        paint_image(cr, &image, pxl_w);
+
+        // preview current stroke
+        let curr_drag = self.imp().curr_drag.borrow();
+        let (r, g, b) = state.palette_data().0[state.palette_sel_idx() as usize].to_cairo();
+        for y in 0..side_len {
+            for x in 0..side_len {
+                if curr_drag.idx_in_range(x, y) {
+                    cr.rectangle(x as f64 * pxl_w, y as f64 * pxl_w, pxl_w, pxl_w);
+                }
+            }
+        }
+        cr.set_source_rgb(r, g, b);
+        let _ = cr.fill();
+
+        // pixel grid
+        for i in 1..side_len {
+            let offset = i as f64 * pxl_w;
+            cr.move_to(offset, 0.0);
+            cr.line_to(offset, width);
+            cr.move_to(0.0, offset);
+            cr.line_to(width, offset);
+        }
+        cr.set_line_width(1.0);
+        cr.set_source_rgba(0.5, 0.5, 0.5, 0.5);
+        let _ = cr.stroke();
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use crate::undo_stack::UndoRedo;
+use crate::widgets::window::Window;
+
+pub struct ChangeTilePixel {
+    // (tile index, pixel index) -> color index
+    before: HashMap<(usize, usize), u8>,
+    after: u8,
+}
+
+impl UndoRedo for ChangeTilePixel {
+    fn undo(&self, state: &Window) {
+        state.modify_tileset_data(|tileset| {
+            for ((t, p), c) in &self.before {
+                if let Some(tile) = tileset.0.get_mut(*t) {
+                    tile.0[*p] = *c;
+                }
+            }
+            true
+        });
+    }
+
+    fn redo(&self, state: &Window) {
+        state.modify_tileset_data(|tileset| {
+            for (t, p) in self.before.keys() {
+                if let Some(tile) = tileset.0.get_mut(*t) {
+                    tile.0[*p] = self.after;
+                }
+            }
+            true
+        });
+    }
+}
+
+impl ChangeTilePixel {
+    pub fn new(before: HashMap<(usize, usize), u8>, after: u8) -> Self {
+        Self { before, after }
+    }
+}
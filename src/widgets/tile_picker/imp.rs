@@ -1,4 +1,4 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 
 use glib::subclass::InitializingObject;
 use glib::Properties;
@@ -9,7 +9,10 @@ use gtk::{Button, CompositeTemplate, DrawingArea, DropDown, Label, StringList};
 
 use strum::IntoEnumIterator;
 
-use crate::data::{list_items::TileSize, tiles::Tileset};
+use crate::data::{
+    list_items::{Bpp, TileSize},
+    tiles::Tileset,
+};
 
 #[derive(Properties, CompositeTemplate, Default)]
 #[template(resource = "/com/example/waffle/tile_picker.ui")]
@@ -29,8 +32,44 @@ pub struct TilePicker {
     #[template_child]
     pub tile_size_items: TemplateChild<StringList>,
 
+    #[template_child]
+    pub browse_bar: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub browse_bpp_select: TemplateChild<DropDown>,
+    #[template_child]
+    pub browse_bpp_items: TemplateChild<StringList>,
+    #[template_child]
+    pub browse_byte_prev: TemplateChild<Button>,
+    #[template_child]
+    pub browse_byte_next: TemplateChild<Button>,
+    #[template_child]
+    pub browse_tile_prev: TemplateChild<Button>,
+    #[template_child]
+    pub browse_tile_next: TemplateChild<Button>,
+    #[template_child]
+    pub browse_row_prev: TemplateChild<Button>,
+    #[template_child]
+    pub browse_row_next: TemplateChild<Button>,
+    #[template_child]
+    pub browse_extract: TemplateChild<Button>,
+    #[template_child]
+    pub browse_close: TemplateChild<Button>,
+
     #[property(name = "row-offset", get, set)]
     row_offset_2: Cell<u32>,
+
+    // browser mode: show any binary file as tiles from a byte offset
+    #[property(get, set)]
+    browsing: Cell<bool>,
+    #[property(get, set)]
+    browse_offset: Cell<u32>,
+    #[property(get, set, builder(Bpp::default()))]
+    browse_bpp: Cell<Bpp>,
+    pub browse_data: RefCell<Vec<u8>>,
+
+    // corner tile idx of the block being dragged out, and of the block picked as the stamp
+    pub stamp_drag: Cell<Option<(u32, u32)>>,
+    pub stamp_range: Cell<Option<(u32, u32)>>,
 }
 
 // The central trait for subclassing a GObject
@@ -62,6 +101,9 @@ impl ObjectImpl for TilePicker {
         for i in TileSize::iter() {
             self.tile_size_items.append(&format!("{}", i));
         }
+        for i in Bpp::iter() {
+            self.browse_bpp_items.append(&format!("{}", i));
+        }
     }
 }
 impl WidgetImpl for TilePicker {}
@@ -1,22 +1,28 @@
 mod imp;
+pub mod utils;
 
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use gio::{ActionEntry, SimpleActionGroup};
+use gio::{ActionEntry, PropertyAction, SimpleActionGroup};
 use glib::clone;
-use gtk::GestureClick;
+use gtk::GestureDrag;
 use gtk::{gio, glib};
 use gtk::{prelude::*, subclass::prelude::*};
 
 use strum::IntoEnumIterator;
 
+use self::utils::*;
 use crate::data::{
     list_items::{Bpp, TileSize},
+    render::render_tiles,
     tiles::Tileset,
 };
 use crate::utils::*;
-use crate::widgets::window::Window;
+use crate::widgets::{
+    tilemap_editor::utils::open_mode7_tileset,
+    window::{draw::paint_image, Window},
+};
 use crate::TILE_W;
 
 glib::wrapper! {
@@ -30,33 +36,81 @@ impl TilePicker {
     pub fn handle_action(&self, state: &Window) {
         let imp = self.imp();
 
-        // mouse click on tileset drawing
-        let gesture = GestureClick::new();
-        gesture.connect_released(clone!(
+        // click on tileset drawing picks a tile, drag picks a block of tiles as a stamp
+        let gesture = GestureDrag::new();
+        gesture.connect_drag_begin(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_, x, y| {
+                // browsed tiles are not part of the tileset
+                if this.browsing() {
+                    return;
+                }
+                if let Some(idx) = this.cursor_to_idx(x, y) {
+                    this.imp().stamp_drag.set(Some((idx, idx)));
+                    this.imp().tile_drawing.queue_draw();
+                }
+            }
+        ));
+        gesture.connect_drag_update(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |drag, dx, dy| {
+                let imp = this.imp();
+                let (Some((start, end)), Some((x, y))) = (imp.stamp_drag.get(), drag.start_point())
+                else {
+                    return;
+                };
+                if let Some(idx) = this.cursor_to_idx(x + dx, y + dy) {
+                    if idx != end {
+                        imp.stamp_drag.set(Some((start, idx)));
+                        imp.tile_drawing.queue_draw();
+                    }
+                }
+            }
+        ));
+        gesture.connect_drag_end(clone!(
             #[weak(rename_to = this)]
             self,
             #[weak]
             state,
-            move |_, _, x, y| {
-                let tile_drawing = &this.imp().tile_drawing;
-                if x < 0.0
-                    || x >= tile_drawing.width().into()
-                    || y < 0.0
-                    || y >= tile_drawing.height().into()
-                {
-                    // coordinate out of range
+            move |_, _, _| {
+                let imp = this.imp();
+                let Some((start, end)) = imp.stamp_drag.take() else {
                     return;
+                };
+                let (tl, br) = (
+                    (start % 16).min(end % 16) + (start / 16).min(end / 16) * 16,
+                    (start % 16).max(end % 16) + (start / 16).max(end / 16) * 16,
+                );
+                // the top left tile is selected either way
+                if tl != state.tileset_sel_idx() {
+                    state.set_tileset_sel_idx(tl);
                 }
-                // account for row offset when calculating correct idx
-                let new_idx =
-                    (this.row_offset() as f64 + y / TILE_W) as u32 * 16 + (x / TILE_W) as u32;
-                if new_idx != state.tileset_sel_idx() {
-                    state.set_tileset_sel_idx(new_idx);
+                match stamp_from_range(&state, tl, br) {
+                    Some(stamp) => {
+                        println!("stamp: {}x{} tiles", stamp.width, stamp.height);
+                        state.set_stamp(Some(stamp));
+                        imp.stamp_range.set(Some((tl, br)));
+                    }
+                    None => state.set_stamp(None),
                 }
+                imp.tile_drawing.queue_draw();
             }
         ));
         imp.tile_drawing.add_controller(gesture);
 
+        // a stamp picked from somewhere else replaces the one picked here
+        state.connect_stamp_notify(clone!(
+            #[weak]
+            imp,
+            move |_| {
+                if imp.stamp_range.take().is_some() {
+                    imp.tile_drawing.queue_draw();
+                }
+            }
+        ));
+
         // tile size dropdown
         imp.tile_size_select.connect_selected_notify(clone!(
             #[weak]
@@ -77,6 +131,10 @@ impl TilePicker {
             #[weak(rename_to = this)]
             self,
             move |_| {
+                if this.browsing() {
+                    this.browse_nudge(-8 * 16 * this.browse_tile_len());
+                    return;
+                }
                 let x = this.row_offset();
                 if x >= 8 {
                     this.set_row_offset(x - 8);
@@ -89,6 +147,10 @@ impl TilePicker {
             #[weak]
             state,
             move |_| {
+                if this.browsing() {
+                    this.browse_nudge(8 * 16 * this.browse_tile_len());
+                    return;
+                }
                 let x = this.row_offset();
                 let max_tiles = state.tileset_data().0.len();
                 if x + 8 + 8 < ((max_tiles + 15) / 16) as u32 {
@@ -97,6 +159,7 @@ impl TilePicker {
             }
         ));
 
+        self.browse_actions(state);
         self.file_actions(state);
     }
 
@@ -113,10 +176,7 @@ impl TilePicker {
             #[weak(rename_to = this)]
             self,
             move |state| {
-                this.set_index_label(
-                    state.tileset_sel_idx() as u16,
-                    state.tileset_data().0.len() as u16 - 1,
-                );
+                this.update_label(state);
                 this.imp().tile_drawing.queue_draw();
             }
         ));
@@ -129,6 +189,30 @@ impl TilePicker {
             }
         ));
 
+        // browser mode
+        self.bind_property("browsing", &self.imp().browse_bar.get(), "visible")
+            .sync_create()
+            .build();
+        self.connect_browsing_notify(clone!(
+            #[weak]
+            state,
+            move |this| {
+                this.update_label(&state);
+                this.imp().tile_drawing.queue_draw();
+            }
+        ));
+        self.connect_browse_offset_notify(clone!(
+            #[weak]
+            state,
+            move |this| {
+                this.update_label(&state);
+                this.imp().tile_drawing.queue_draw();
+            }
+        ));
+        self.connect_browse_bpp_notify(|this| {
+            this.imp().tile_drawing.queue_draw();
+        });
+
         state.connect_palette_data_notify(clone!(
             #[weak(rename_to = this)]
             self,
@@ -148,11 +232,29 @@ impl TilePicker {
         state.connect_tile_size_notify(clone!(
             #[weak(rename_to = this)]
             self,
-            move |_| {
+            move |state| {
+                // tile size can also be set by opening a project
+                if let Some(i) = TileSize::iter().position(|s| s == state.tile_size()) {
+                    this.imp().tile_size_select.set_selected(i as u32);
+                }
                 this.imp().tile_drawing.queue_draw();
             }
         ));
 
+        state.connect_mode7_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |state| {
+                // mode 7 only uses 8x8 tiles
+                let imp = this.imp();
+                if state.mode7() {
+                    imp.tile_size_select.set_selected(0);
+                    this.set_row_offset(0);
+                }
+                imp.tile_size_select.set_sensitive(!state.mode7());
+            }
+        ));
+
         self.imp().tile_drawing.set_draw_func(clone!(
             #[weak(rename_to = this)]
             self,
@@ -168,25 +270,67 @@ impl TilePicker {
 
                 let tile_w = w as f64 / 16.0;
 
+                if this.browsing() {
+                    // decode on the fly, tiles past the end of the file are drawn pink
+                    let bpp = this.browse_bpp();
+                    let tiles = Tileset::decode_at(
+                        &this.imp().browse_data.borrow(),
+                        this.browse_offset() as usize,
+                        bpp,
+                        256,
+                    );
+                    let indices: Vec<usize> = (0..256).collect();
+                    let image = render_tiles(
+                        &tiles,
+                        &state.palette_data(),
+                        &indices,
+                        16,
+                        state.palette_start(bpp),
+                        state.render_options(bpp),
+                    );
+                    paint_image(cr, &image, tile_w / 8.0);
+                    return;
+                }
+
                 // 16 8x8 tiles per row
-                for i in 0..256 {
-                    let ti = (i + row_offset * 16) as usize;
-                    if ti >= tiles.0.len() {
-                        break;
-                    }
-                    let x_offset = (i % 16) as f64 * tile_w;
-                    let y_offset = (i / 16) as f64 * tile_w;
-                    let _ = cr.save();
-                    cr.translate(x_offset, y_offset);
-                    tiles.draw_tile(ti, cr, &state, None);
-                    let _ = cr.restore();
+                let first = row_offset as usize * 16;
+                let indices: Vec<usize> = (first..tiles.0.len().min(first + 256)).collect();
+                if !indices.is_empty() {
+                    let bpp = state.tile_bpp();
+                    let image = render_tiles(
+                        tiles,
+                        &state.palette_data(),
+                        &indices,
+                        16,
+                        state.palette_start(bpp),
+                        state.render_options(bpp),
+                    );
+                    paint_image(cr, &image, tile_w / 8.0);
                 }
 
                 // draw selected tile outline
                 let _ = cr.save();
                 cr.translate(0.0, -(row_offset as f64) * tile_w);
+                let imp = this.imp();
                 let idx = state.tileset_sel_idx();
                 let tile_size = state.tile_size();
+                if let Some((start, end)) = imp.stamp_drag.get().or(imp.stamp_range.get()) {
+                    // outline of the block of tiles picked as a stamp, in whole 16x16 tiles
+                    let step = tile_size as u32 / 8;
+                    let (x0, x1) = ((start % 16).min(end % 16), (start % 16).max(end % 16));
+                    let (y0, y1) = ((start / 16).min(end / 16), (start / 16).max(end / 16));
+                    cr.set_source_rgb(0.8, 0.8, 0.0);
+                    cr.rectangle(
+                        x0 as f64 * tile_w,
+                        y0 as f64 * tile_w,
+                        (((x1 - x0) / step + 1) * step) as f64 * tile_w,
+                        (((y1 - y0) / step + 1) * step) as f64 * tile_w,
+                    );
+                    cr.set_line_width(2.0);
+                    let _ = cr.stroke();
+                    let _ = cr.restore();
+                    return;
+                }
                 if state.is_valid_tileset_idx() {
                     cr.set_source_rgb(0.8, 0.8, 0.0);
                 } else {
@@ -216,12 +360,123 @@ impl TilePicker {
         ));
     }
 
+    // idx of the tile under the cursor, accounting for row offset
+    fn cursor_to_idx(&self, x: f64, y: f64) -> Option<u32> {
+        let tile_drawing = &self.imp().tile_drawing;
+        if x < 0.0
+            || x >= tile_drawing.width().into()
+            || y < 0.0
+            || y >= tile_drawing.height().into()
+        {
+            // coordinate out of range
+            return None;
+        }
+        Some((self.row_offset() as f64 + y / TILE_W) as u32 * 16 + (x / TILE_W) as u32)
+    }
+
     fn set_index_label(&self, idx: u16, max: u16) {
         self.imp()
             .tile_idx_label
             .set_label(&format!("${:03X} / ${:03X}", idx, max));
     }
 
+    fn update_label(&self, state: &Window) {
+        if self.browsing() {
+            let len = self.imp().browse_data.borrow().len();
+            self.imp().tile_idx_label.set_label(&format!(
+                "${:06X} / ${:06X}",
+                self.browse_offset(),
+                len
+            ));
+        } else {
+            self.set_index_label(
+                state.tileset_sel_idx() as u16,
+                state.tileset_data().0.len() as u16 - 1,
+            );
+        }
+    }
+
+    // bytes per tile in browser mode
+    fn browse_tile_len(&self) -> isize {
+        8 * self.browse_bpp().bits() as isize
+    }
+
+    // move the browser offset, staying inside the file
+    fn browse_nudge(&self, delta: isize) {
+        let len = self.imp().browse_data.borrow().len() as isize;
+        let offset = (self.browse_offset() as isize + delta).clamp(0, (len - 1).max(0));
+        self.set_browse_offset(offset as u32);
+    }
+
+    fn browse_actions(&self, state: &Window) {
+        let imp = self.imp();
+
+        imp.browse_bpp_select.connect_selected_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |select| {
+                let bpp = Bpp::iter().nth(select.selected() as usize).unwrap();
+                this.set_browse_bpp(bpp);
+            }
+        ));
+
+        // (button, bytes, tiles) to move by
+        for (button, bytes, tiles) in [
+            (&imp.browse_byte_prev, -1, 0),
+            (&imp.browse_byte_next, 1, 0),
+            (&imp.browse_tile_prev, 0, -1),
+            (&imp.browse_tile_next, 0, 1),
+            (&imp.browse_row_prev, 0, -16),
+            (&imp.browse_row_next, 0, 16),
+        ] {
+            button.connect_clicked(clone!(
+                #[weak(rename_to = this)]
+                self,
+                move |_| {
+                    this.browse_nudge(bytes + tiles * this.browse_tile_len());
+                }
+            ));
+        }
+
+        imp.browse_extract.connect_clicked(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            state,
+            move |_| {
+                let remaining =
+                    this.imp().browse_data.borrow().len() - this.browse_offset() as usize;
+                let default_count = (remaining / this.browse_tile_len() as usize).min(256);
+                number_dialog(
+                    &state,
+                    "Extract Tiles",
+                    "Number of tiles",
+                    default_count,
+                    clone!(
+                        #[weak]
+                        this,
+                        #[weak]
+                        state,
+                        move |count| {
+                            file_save_dialog(&state.clone(), move |_, filepath| {
+                                extract_tiles(&this, &state, filepath, count);
+                            });
+                        }
+                    ),
+                );
+            }
+        ));
+
+        imp.browse_close.connect_clicked(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |_| {
+                this.set_browsing(false);
+                this.imp().browse_data.take();
+            }
+        ));
+    }
+
     fn file_actions(&self, state: &Window) {
         let action_open = ActionEntry::builder("open")
             .parameter_type(Some(&String::static_variant_type()))
@@ -239,25 +494,118 @@ impl TilePicker {
                     let bpp = Bpp::from_str(&bpp).expect("invalid bit depth");
 
                     file_open_dialog(state.clone(), move |path| {
-                        match Tileset::from_file(&path, bpp) {
-                            Err(e) => {
-                                eprintln!("Error: {}", e);
-                            }
-                            Ok(t) => {
-                                println!("load tileset: {path:?}");
-                                state.set_tileset_data(t);
-                                state.set_tileset_sel_idx(0);
-                                state.set_tile_bpp(bpp);
-                                this.set_row_offset(0);
-                                state.set_tileset_file(Some(path));
-                                state.clear_history();
-                            }
+                        // check for unsaved data
+                        if state.tileset_dirty() {
+                            unsaved_tileset_dialog(
+                                &state,
+                                clone!(
+                                    #[weak]
+                                    this,
+                                    #[weak]
+                                    state,
+                                    move || {
+                                        if let Err(e) = open_file(&this, &state, path.clone(), bpp)
+                                        {
+                                            eprintln!("Error: {e}");
+                                        }
+                                    }
+                                ),
+                            );
+                        } else if let Err(e) = open_file(&this, &state, path, bpp) {
+                            eprintln!("Error: {e}");
                         }
                     });
                 }
             ))
             .build();
 
+        let action_open_rom = ActionEntry::builder("openrom")
+            .parameter_type(Some(&String::static_variant_type()))
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, parameter| {
+                    // parse bit depth parameter
+                    let Some(bpp) = parameter else { return };
+                    let bpp = bpp
+                        .get::<String>()
+                        .expect("parameter should have type String");
+                    let bpp = Bpp::from_str(&bpp).expect("invalid bit depth");
+
+                    file_open_dialog(state.clone(), move |path| {
+                        // default to 256 tiles
+                        rom_address_dialog(
+                            &state.clone(),
+                            256 * 8 * bpp.bits() as usize,
+                            clone!(
+                                #[weak]
+                                this,
+                                #[weak]
+                                state,
+                                move |kind, addr, len| {
+                                    let path = path.clone();
+                                    // check for unsaved data
+                                    if state.tileset_dirty() {
+                                        unsaved_tileset_dialog(
+                                            &state,
+                                            clone!(
+                                                #[weak]
+                                                this,
+                                                #[weak]
+                                                state,
+                                                move || {
+                                                    if let Err(e) = open_rom(
+                                                        &this,
+                                                        &state,
+                                                        path.clone(),
+                                                        bpp,
+                                                        kind,
+                                                        addr,
+                                                        len,
+                                                    ) {
+                                                        eprintln!("Error: {e}");
+                                                    }
+                                                }
+                                            ),
+                                        );
+                                    } else if let Err(e) =
+                                        open_rom(&this, &state, path, bpp, kind, addr, len)
+                                    {
+                                        eprintln!("Error: {e}");
+                                    }
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+
+        let action_browse = ActionEntry::builder("browse")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog(
+                        state.clone(),
+                        clone!(
+                            #[weak]
+                            this,
+                            #[weak]
+                            state,
+                            move |path| {
+                                open_browse(&this, &state, path);
+                            }
+                        ),
+                    );
+                }
+            ))
+            .build();
+
         let action_reload = ActionEntry::builder("reload")
             .activate(clone!(
                 #[weak(rename_to = this)]
@@ -266,36 +614,174 @@ impl TilePicker {
                 state,
                 move |_, _, _| {
                     let Some(path) = state.tileset_file() else {
-                        eprintln!("No palette file currently open");
+                        eprintln!("No tileset file currently open");
                         return;
                     };
                     let bpp = state.tile_bpp();
-                    match Tileset::from_file(&path, bpp) {
-                        Err(e) => {
-                            eprintln!("Error: {}", e);
-                        }
-                        Ok(t) => {
-                            println!("reload tileset: {path:?}");
-                            state.set_tileset_data(t);
-                            state.set_tileset_sel_idx(0);
-                            this.set_row_offset(0);
-                            state.clear_history();
+
+                    let reload = clone!(
+                        #[weak]
+                        this,
+                        #[weak]
+                        state,
+                        move || {
+                            if state.tileset_mode7() {
+                                if let Err(e) = open_mode7_tileset(&state, path.clone()) {
+                                    eprintln!("Error: {e}");
+                                }
+                            } else if let Some(region) =
+                                state.tileset_rom().filter(|r| r.path == path)
+                            {
+                                reload_rom(&state, region, bpp);
+                            } else if let Err(e) = open_file(&this, &state, path.clone(), bpp) {
+                                eprintln!("Error: {e}");
+                            }
                         }
+                    );
+                    if state.tileset_dirty() {
+                        unsaved_tileset_dialog(&state, reload);
+                    } else {
+                        reload();
                     }
                 }
             ))
             .build();
 
+        let action_save = ActionEntry::builder("save")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    let Some(filepath) = state.tileset_file() else {
+                        return;
+                    };
+                    if let Err(e) = save_file(&state, filepath) {
+                        eprintln!("Error saving file: {e}");
+                    }
+                }
+            ))
+            .build();
+
+        let action_save_as = ActionEntry::builder("saveas")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_save_dialog(&state.clone(), move |_, filepath| {
+                        if let Err(e) = save_file(&state, filepath) {
+                            eprintln!("Error saving file: {e}");
+                        }
+                    });
+                }
+            ))
+            .build();
+
+        let action_export_png = ActionEntry::builder("exportpng")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    png_save_dialog(&state.clone(), &[], move |_, filepath| {
+                        export_png(&state, filepath);
+                    });
+                }
+            ))
+            .build();
+
+        let action_export_header = ActionEntry::builder("exportheader")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    c_header_save_dialog(
+                        &state.clone(),
+                        clone!(
+                            #[weak]
+                            state,
+                            move |header, filepath| {
+                                export_header(&state, header, filepath);
+                            }
+                        ),
+                    );
+                }
+            ))
+            .build();
+
+        let action_export_source = ActionEntry::builder("exportsource")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    source_save_dialog(&state.clone(), move |source, filepath| {
+                        export_source(&state, source, filepath);
+                    });
+                }
+            ))
+            .build();
+
+        let action_import_png = ActionEntry::builder("importpng")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    png_open_dialog(state.clone(), move |path| {
+                        // check for unsaved data
+                        if state.tileset_dirty() {
+                            unsaved_tileset_dialog(
+                                &state,
+                                clone!(
+                                    #[weak]
+                                    this,
+                                    #[weak]
+                                    state,
+                                    move || {
+                                        import_png(&this, &state, path.clone());
+                                    }
+                                ),
+                            );
+                        } else {
+                            import_png(&this, &state, path);
+                        }
+                    });
+                }
+            ))
+            .build();
+
         let actions = SimpleActionGroup::new();
-        actions.add_action_entries([action_open, action_reload]);
+        actions.add_action_entries([
+            action_open,
+            action_open_rom,
+            action_browse,
+            action_reload,
+            action_save,
+            action_save_as,
+            action_export_png,
+            action_import_png,
+            action_export_source,
+            action_export_header,
+        ]);
+        // compression used when opening and saving
+        actions.add_action(&PropertyAction::new(
+            "compression",
+            state,
+            "tileset_compression",
+        ));
 
-        // bind file to reload action
+        // bind file to action
         let reload = actions.lookup_action("reload").unwrap();
         state
             .bind_property("tileset_file", &reload, "enabled")
             .transform_to(|_, file: Option<PathBuf>| Some(file.is_some()))
             .sync_create()
             .build();
+        let save = actions.lookup_action("save").unwrap();
+        state
+            .bind_property("tileset_file", &save, "enabled")
+            .transform_to(|_, file: Option<PathBuf>| Some(file.is_some()))
+            .sync_create()
+            .build();
 
         state.insert_action_group("tiles", Some(&actions));
     }
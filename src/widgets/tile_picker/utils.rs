@@ -0,0 +1,283 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use gtk::glib::{self, clone};
+use gtk::subclass::prelude::*;
+
+use crate::data::{
+    image,
+    list_items::{Bpp, TileSize},
+    rom::{AddressKind, RomRegion},
+    source::{CHeader, Source},
+    tilemap::TileBlock,
+    tiles::Tileset,
+};
+use crate::utils::*;
+use crate::widgets::{tile_picker::TilePicker, window::Window};
+
+pub fn open_file(
+    picker: &TilePicker,
+    state: &Window,
+    filepath: PathBuf,
+    bpp: Bpp,
+) -> std::io::Result<()> {
+    let content = state.tileset_compression().read_file(&filepath)?;
+    let res = Tileset::from_bytes(&content, bpp)?;
+    println!("load tileset: {filepath:?}");
+    state.set_tileset_mode7(false);
+    state.set_tileset_data(res);
+    state.set_tileset_sel_idx(0);
+    state.set_tile_bpp(bpp);
+    picker.set_row_offset(0);
+    state.set_tileset_file(Some(filepath));
+    state.set_tileset_rom(None);
+    state.mark_tileset_clean();
+    state.clear_history();
+    Ok(())
+}
+
+pub fn open_rom(
+    picker: &TilePicker,
+    state: &Window,
+    filepath: PathBuf,
+    bpp: Bpp,
+    kind: AddressKind,
+    addr: usize,
+    len: usize,
+) -> std::io::Result<()> {
+    let (region, content) =
+        RomRegion::read(&filepath, kind, addr, len, state.tileset_compression())?;
+    let res = Tileset::from_bytes(&content, bpp)?;
+    println!(
+        "load tileset from rom: {filepath:?} at {:#X}",
+        region.offset
+    );
+    state.set_tileset_mode7(false);
+    state.set_tileset_data(res);
+    state.set_tileset_sel_idx(0);
+    state.set_tile_bpp(bpp);
+    picker.set_row_offset(0);
+    state.set_tileset_file(Some(filepath));
+    state.set_tileset_rom(Some(region));
+    state.mark_tileset_clean();
+    state.clear_history();
+    Ok(())
+}
+
+pub fn reload_rom(state: &Window, region: RomRegion, bpp: Bpp) {
+    match region
+        .reload(state.tileset_compression())
+        .and_then(|content| Tileset::from_bytes(&content, bpp))
+    {
+        Ok(res) => {
+            println!("reload tileset from rom: {:?}", region.path);
+            state.set_tileset_data(res);
+            state.set_tileset_sel_idx(0);
+            state.mark_tileset_clean();
+            state.clear_history();
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+pub fn save_file(state: &Window, filepath: PathBuf) -> std::io::Result<()> {
+    // write back into the rom it was opened from
+    if let Some(region) = state.tileset_rom().filter(|r| r.path == filepath) {
+        // the rom can only be patched, never replaced by a tileset file
+        if state.tileset_mode7() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "can't save a mode 7 tileset into a rom",
+            ));
+        }
+        let content = state.tileset_data().to_bytes(state.tile_bpp());
+        region.patch(&content, state.tileset_compression())?;
+        println!("patch tileset: {filepath:?} at {:#X}", region.offset);
+        state.mark_tileset_clean();
+        return Ok(());
+    }
+
+    let f = File::create(filepath.clone())?;
+    if state.tileset_mode7() {
+        state.tileset_data().write_file_mode7(&f)?;
+    } else {
+        let content = state.tileset_data().to_bytes(state.tile_bpp());
+        state.tileset_compression().write_file(&f, &content)?;
+    }
+    println!("save tileset: {filepath:?}");
+    state.set_tileset_file(Some(filepath));
+    state.mark_tileset_clean();
+    Ok(())
+}
+
+pub fn unsaved_tileset_dialog(state: &Window, after: impl Fn() + Clone + 'static) {
+    let message = if let Some(file) = state.tileset_file() {
+        format!("Save tileset changes to \"{}\"?", file.display())
+    } else {
+        "Save tileset changes to new file?".to_string()
+    };
+
+    let after2 = after.clone();
+    save_changes_dialog(
+        state,
+        message,
+        clone!(
+            #[weak]
+            state,
+            move || {
+                let after1 = after.clone();
+                if let Some(filepath) = state.tileset_file() {
+                    // save to tileset_file
+                    match save_file(&state, filepath.clone()) {
+                        Ok(_) => after1(),
+                        Err(e) => eprintln!("Error saving file: {e}"),
+                    }
+                } else {
+                    // save to new file
+                    file_save_dialog(&state.clone(), move |_, filepath| {
+                        match save_file(&state, filepath) {
+                            Ok(_) => after1(),
+                            Err(e) => eprintln!("Error saving file: {e}"),
+                        }
+                    });
+                }
+            }
+        ),
+        clone!(
+            #[weak]
+            state,
+            move || {
+                println!("discard unsaved tileset");
+                state.mark_tileset_clean();
+                after2();
+            }
+        ),
+    );
+}
+
+// 16 tiles wide indexed image, using the selected subpalette
+pub fn export_png(state: &Window, filepath: PathBuf) {
+    let bpp = state.tile_bpp();
+    let start = match bpp {
+        Bpp::Eight => 0,
+        Bpp::Two | Bpp::Four => state.curr_palette_start() as usize,
+    };
+    let colors = &state.palette_data().0[start..start + bpp.to_val() as usize];
+    match File::create(filepath.clone())
+        .and_then(|f| image::write_tileset_png(&state.tileset_data(), colors, bpp, &f))
+    {
+        Ok(_) => println!("export tileset image: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting image: {e}"),
+    }
+}
+
+pub fn import_png(picker: &TilePicker, state: &Window, filepath: PathBuf) {
+    let bpp = state.tile_bpp();
+    match image::read_tileset_png(&filepath, bpp) {
+        Ok(res) => {
+            println!("import tileset image: {filepath:?}");
+            state.set_tileset_mode7(false);
+            state.set_tileset_data(res);
+            state.set_tileset_sel_idx(0);
+            picker.set_row_offset(0);
+            // imported data has not been saved anywhere yet
+            state.set_tileset_file(None::<PathBuf>);
+            state.clear_history();
+            state.mark_tileset_dirty();
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+// show a binary file as tiles in browser mode
+pub fn open_browse(picker: &TilePicker, state: &Window, filepath: PathBuf) {
+    match std::fs::read(&filepath) {
+        Ok(content) => {
+            println!("browse: {filepath:?}");
+            let imp = picker.imp();
+            imp.browse_data.replace(content);
+            imp.browse_bpp_select.set_selected(state.tile_bpp() as u32);
+            picker.set_browse_offset(0);
+            picker.set_browsing(true);
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+// save count tiles from the browser offset as a tileset file
+pub fn extract_tiles(picker: &TilePicker, state: &Window, filepath: PathBuf, count: usize) {
+    if count > Tileset::MAX {
+        eprintln!(
+            "Error: tile count exceeds maximum of {} tiles",
+            Tileset::MAX
+        );
+        return;
+    }
+    let tile_len = 8 * picker.browse_bpp().bits() as usize;
+    let data = picker.imp().browse_data.borrow();
+    let start = picker.browse_offset() as usize;
+    // whole tiles only
+    let end = (start + count * tile_len).min(data.len());
+    let end = end - (end - start) % tile_len;
+    if end == start {
+        eprintln!("Error: no tiles to extract");
+        return;
+    }
+
+    match File::create(filepath.clone()).and_then(|f| {
+        state
+            .tileset_compression()
+            .write_file(&f, &data[start..end])
+    }) {
+        Ok(_) => println!(
+            "extract {} tiles at {start:#X}: {filepath:?}",
+            (end - start) / tile_len
+        ),
+        Err(e) => eprintln!("Error saving file: {e}"),
+    }
+}
+
+pub fn export_source(state: &Window, source: Source, filepath: PathBuf) {
+    match File::create(filepath.clone()).and_then(|f| {
+        source.write_tileset(
+            &state.tileset_data(),
+            state.tile_bpp(),
+            state.tileset_mode7(),
+            &f,
+        )
+    }) {
+        Ok(_) => println!("export tileset source: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting source: {e}"),
+    }
+}
+
+pub fn export_header(state: &Window, header: CHeader, filepath: PathBuf) {
+    match File::create(filepath.clone()).and_then(|f| {
+        header.write_tileset(
+            &state.tileset_data(),
+            state.tile_bpp(),
+            state.tileset_mode7(),
+            &f,
+        )
+    }) {
+        Ok(_) => println!("export tileset header: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting header: {e}"),
+    }
+}
+
+// tiles between tileset idx tl and br (inclusive corners) as a stamp, None for a single tile
+pub fn stamp_from_range(state: &Window, tl: u32, br: u32) -> Option<TileBlock> {
+    // a 16x16 tile takes up 2x2 tileset tiles
+    let step = match state.tile_size() {
+        TileSize::Eight => 1,
+        TileSize::Sixteen => 2,
+    };
+    match TileBlock::from_tile_range(tl, br, step, state.curr_palette()) {
+        Ok(block) if block.tiles.len() > 1 => Some(block),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("{e}");
+            None
+        }
+    }
+}
@@ -9,8 +9,8 @@ use gtk::{CompositeTemplate, DrawingArea, DropDown, ScrolledWindow, StringList,
 use strum::IntoEnumIterator;
 
 use crate::data::{
-    list_items::{BGModeTwo, DrawMode, Zoom},
-    tilemap::Tile,
+    list_items::{BGModeTwo, DrawMode, FillMatch, Zoom},
+    tilemap::{Tile, TileBlock},
 };
 
 #[derive(Properties, CompositeTemplate, Default)]
@@ -34,6 +34,18 @@ pub struct TilemapEditor {
     #[template_child]
     pub rect_fill_btn: TemplateChild<ToggleButton>,
     #[template_child]
+    pub rect_btn: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub line_btn: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub fill_btn: TemplateChild<ToggleButton>,
+    #[template_child]
+    pub fill_match_select: TemplateChild<DropDown>,
+    #[template_child]
+    pub fill_match_list: TemplateChild<StringList>,
+    #[template_child]
+    pub select_btn: TemplateChild<ToggleButton>,
+    #[template_child]
     pub flip_x_btn: TemplateChild<ToggleButton>,
     #[template_child]
     pub flip_y_btn: TemplateChild<ToggleButton>,
@@ -44,8 +56,15 @@ pub struct TilemapEditor {
     tilemap_zoom: Cell<Zoom>,
     pub curr_tile: RefCell<Tile>,
     pub curr_drag: RefCell<DrawMode>,
+    // top left and bottom right corner of the selected tiles
+    pub selection: Cell<Option<Rect>>,
+    pub clipboard: RefCell<Option<TileBlock>>,
+    // the stamp repeats from where the pen stroke started
+    pub pen_origin: Cell<(usize, usize)>,
 }
 
+type Rect = ((usize, usize), (usize, usize));
+
 // The central trait for subclassing a GObject
 #[glib::object_subclass]
 impl ObjectSubclass for TilemapEditor {
@@ -73,6 +92,11 @@ impl ObjectImpl for TilemapEditor {
         }
         self.zoom_select.set_selected(Zoom::default() as u32);
 
+        // setup fill match dropdown
+        for i in FillMatch::iter() {
+            self.fill_match_list.append(&i.to_string());
+        }
+
         // setup bg mode dropdown
         for i in BGModeTwo::iter() {
             self.mode_list.append(&i.to_string());
@@ -4,21 +4,28 @@ pub mod utils;
 
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use strum::IntoEnumIterator;
 
-use gio::{ActionEntry, SimpleActionGroup};
+use gio::{ActionEntry, PropertyAction, SimpleActionGroup};
 use glib::clone;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
-use gtk::GestureDrag;
+use gtk::{gdk, GestureClick, GestureDrag};
 use gtk::{gio, glib};
 
-use self::operation::ChangeTilemapTile;
-
-use crate::data::list_items::{BGModeTwo, Bpp, DrawMode, TileSize, Zoom};
+use crate::data::{
+    list_items::{BGModeTwo, Bpp, DrawMode, FillMatch, TilemapSize, Zoom},
+    render::render_tilemap,
+    tilemap::{Tile, TileBlock, Tilemap},
+};
 use crate::utils::*;
-use crate::widgets::{tilemap_editor::utils::*, window::Window};
+use crate::widgets::{
+    tile_picker::utils::unsaved_tileset_dialog,
+    tilemap_editor::utils::*,
+    window::{draw::paint_image, Window},
+};
 use crate::TILE_W;
 
 glib::wrapper! {
@@ -58,11 +65,12 @@ impl TilemapEditor {
             }
         ));
 
-        // change current tile
+        // change current tile, picking a single tile drops the stamp
         state.connect_tileset_sel_idx_notify(clone!(
             #[weak]
             imp,
             move |state| {
+                state.set_stamp(None);
                 if let Err(e) = imp
                     .curr_tile
                     .borrow_mut()
@@ -115,20 +123,40 @@ impl TilemapEditor {
             }
         ));
 
+        // match options only apply to the fill tool
+        imp.fill_btn
+            .bind_property("active", &imp.fill_match_select.get(), "visible")
+            .sync_create()
+            .build();
+
+        // selection only shows while the select tool is active
+        imp.select_btn.connect_active_notify(clone!(
+            #[weak]
+            imp,
+            move |btn| {
+                if !btn.is_active() {
+                    imp.selection.set(None);
+                    imp.tilemap_drawing.queue_draw();
+                }
+            }
+        ));
+
         self.file_actions(state);
+        self.mode7_actions(state);
     }
 
     pub fn render_widget(&self, state: &Window) {
         self.connect_tilemap_zoom_notify(clone!(
             #[weak(rename_to = this)]
             self,
+            #[weak]
+            state,
             move |_| {
-                let imp = this.imp();
-                let side_length = (TILE_W * 32.0 * this.tilemap_zoom().to_val()) as i32;
-                imp.tilemap_drawing.set_content_width(side_length);
-                imp.tilemap_drawing.set_content_height(side_length);
-
-                imp.tilemap_drawing.queue_draw();
+                // zoom can also be set by opening a project
+                this.imp()
+                    .zoom_select
+                    .set_selected(this.tilemap_zoom() as u32);
+                this.resize_drawing(&state);
             }
         ));
 
@@ -159,7 +187,9 @@ impl TilemapEditor {
         state.connect_bg_mode_notify(clone!(
             #[weak(rename_to = this)]
             self,
-            move |_| {
+            move |state| {
+                // bg mode can also be set by loading a scene
+                this.imp().mode_select.set_selected(state.bg_mode() as u32);
                 this.imp().tilemap_drawing.queue_draw();
             }
         ));
@@ -167,8 +197,21 @@ impl TilemapEditor {
         state.connect_tilemap_data_notify(clone!(
             #[weak(rename_to = this)]
             self,
-            move |_| {
-                this.imp().tilemap_drawing.queue_draw();
+            move |state| {
+                // a smaller tilemap may not contain the selection anymore
+                let imp = this.imp();
+                let (width, height) = {
+                    let tilemap = state.tilemap_data();
+                    (tilemap.width, tilemap.height)
+                };
+                if imp
+                    .selection
+                    .get()
+                    .is_some_and(|(_, br)| br.0 >= width || br.1 >= height)
+                {
+                    imp.selection.set(None);
+                }
+                this.resize_drawing(state);
             }
         ));
 
@@ -182,6 +225,21 @@ impl TilemapEditor {
             }
         ));
 
+        state.connect_mode7_notify(clone!(
+            #[weak(rename_to = this)]
+            self,
+            move |state| {
+                // mode 7 tilemaps only store tile index
+                let imp = this.imp();
+                for btn in [&imp.flip_x_btn, &imp.flip_y_btn, &imp.priority_btn] {
+                    if state.mode7() {
+                        btn.set_active(false);
+                    }
+                    btn.set_sensitive(!state.mode7());
+                }
+            }
+        ));
+
         self.imp().tilemap_drawing.set_draw_func(clone!(
             #[weak(rename_to = this)]
             self,
@@ -206,18 +264,21 @@ impl TilemapEditor {
         drag_event.connect_drag_begin(clone!(
             #[weak(rename_to = this)]
             self,
+            #[weak]
+            state,
             move |_, x, y| {
                 let imp = this.imp();
 
                 // calculate tile index
-                let Some(new_idx) = this.cursor_to_idx(x, y) else {
+                let Some(new_idx) = this.cursor_to_idx(&state, x, y) else {
                     return;
                 };
-                let idx = (new_idx % 32, new_idx / 32);
+                let width = state.tilemap_data().width;
+                let idx = (new_idx % width, new_idx / width);
 
                 if imp.pen_draw_btn.is_active() {
-                    let mut set = HashSet::new();
-                    set.insert(idx);
+                    imp.pen_origin.set(idx);
+                    let set = HashSet::from_iter(this.pen_cells(&state, idx));
                     imp.curr_drag.replace(DrawMode::Pen(set));
                     imp.tilemap_drawing.queue_draw();
                 } else if imp.rect_fill_btn.is_active() {
@@ -226,6 +287,39 @@ impl TilemapEditor {
                         end: idx,
                     });
                     imp.tilemap_drawing.queue_draw();
+                } else if imp.rect_btn.is_active() {
+                    imp.curr_drag.replace(DrawMode::Rect {
+                        start: idx,
+                        end: idx,
+                    });
+                    imp.tilemap_drawing.queue_draw();
+                } else if imp.line_btn.is_active() {
+                    imp.curr_drag.replace(DrawMode::Line {
+                        start: idx,
+                        end: idx,
+                    });
+                    imp.tilemap_drawing.queue_draw();
+                } else if imp.fill_btn.is_active() {
+                    let region = this.fill_region(&state, idx);
+                    imp.curr_drag.replace(DrawMode::Fill(region));
+                    imp.tilemap_drawing.queue_draw();
+                } else if imp.select_btn.is_active() {
+                    // drag inside the selection moves it, drag outside starts a new one
+                    let inside = imp.selection.get().is_some_and(|(tl, br)| {
+                        (tl.0..=br.0).contains(&idx.0) && (tl.1..=br.1).contains(&idx.1)
+                    });
+                    imp.curr_drag.replace(if inside {
+                        DrawMode::Move {
+                            start: idx,
+                            end: idx,
+                        }
+                    } else {
+                        DrawMode::Select {
+                            start: idx,
+                            end: idx,
+                        }
+                    });
+                    imp.tilemap_drawing.queue_draw();
                 } else {
                     eprintln!("draw mode not selected");
                 }
@@ -234,6 +328,8 @@ impl TilemapEditor {
         drag_event.connect_drag_update(clone!(
             #[weak(rename_to = this)]
             self,
+            #[weak]
+            state,
             move |drag, dx, dy| {
                 let imp = this.imp();
 
@@ -241,24 +337,35 @@ impl TilemapEditor {
                 let Some((x, y)) = drag.start_point() else {
                     return;
                 };
-                let Some(new_idx) = this.cursor_to_idx(x + dx, y + dy) else {
+                let Some(new_idx) = this.cursor_to_idx(&state, x + dx, y + dy) else {
                     return;
                 };
 
-                let new_idx_2d = (new_idx % 32, new_idx / 32);
+                let width = state.tilemap_data().width;
+                let new_idx_2d = (new_idx % width, new_idx / width);
 
                 match &mut *imp.curr_drag.borrow_mut() {
                     DrawMode::Pen(set) => {
-                        if set.insert(new_idx_2d) {
+                        let mut changed = false;
+                        for cell in this.pen_cells(&state, new_idx_2d) {
+                            changed |= set.insert(cell);
+                        }
+                        if changed {
                             imp.tilemap_drawing.queue_draw();
                         }
                     }
-                    DrawMode::RectFill { start: _, end } => {
+                    DrawMode::RectFill { start: _, end }
+                    | DrawMode::Rect { start: _, end }
+                    | DrawMode::Line { start: _, end }
+                    | DrawMode::Select { start: _, end }
+                    | DrawMode::Move { start: _, end } => {
                         if *end != new_idx_2d {
                             *end = new_idx_2d;
                             imp.tilemap_drawing.queue_draw();
                         }
                     }
+                    // the fill region is set when the button is pressed
+                    DrawMode::Fill(_) => {}
                     _ => {
                         eprintln!("draw mode not selected");
                     }
@@ -274,60 +381,92 @@ impl TilemapEditor {
                 let imp = this.imp();
                 let state = &state;
 
+                // the borrow must end before curr_drag is replaced
+                let cells = this.stamp_cells(state, &imp.curr_drag.borrow());
+                if let Some(cells) = cells {
+                    write_cells(state, &cells);
+                    imp.curr_drag.replace(DrawMode::None);
+                    return;
+                }
+
                 match *imp.curr_drag.borrow() {
-                    DrawMode::Pen(ref set) => {
-                        let new_tile = this.imp().curr_tile.borrow();
-                        state.modify_tilemap_data(move |tilemap| {
-                            let mut map = HashMap::new();
-                            for (x, y) in set {
-                                if tilemap.0[y * 32 + x] != *new_tile {
-                                    map.insert((*x, *y), tilemap.0[y * 32 + x]);
-                                    tilemap.0[y * 32 + x] = *new_tile;
-                                }
-                            }
-                            if !map.is_empty() {
-                                state.push_op(ChangeTilemapTile::new(map, *new_tile).into());
-                                return true;
-                            } else {
-                                return false;
-                            }
-                        });
+                    DrawMode::Select { start, end } => {
+                        imp.selection.set(Some((
+                            (start.0.min(end.0), start.1.min(end.1)),
+                            (start.0.max(end.0), start.1.max(end.1)),
+                        )));
+                        imp.tilemap_drawing.queue_draw();
                     }
-                    DrawMode::RectFill { start, end } => {
-                        let new_tile = this.imp().curr_tile.borrow();
-                        state.modify_tilemap_data(move |tilemap| {
-                            let ((x_min, x_max), (y_min, y_max)) = (
-                                (start.0.min(end.0), start.0.max(end.0)),
-                                (start.1.min(end.1), start.1.max(end.1)),
-                            );
-                            let mut map = HashMap::new();
-                            for i in y_min..=y_max {
-                                for j in x_min..=x_max {
-                                    if tilemap.0[i * 32 + j] != *new_tile {
-                                        map.insert((j, i), tilemap.0[i * 32 + j]);
-                                        tilemap.0[i * 32 + j] = *new_tile;
-                                    }
-                                }
-                            }
-                            if !map.is_empty() {
-                                state.push_op(ChangeTilemapTile::new(map, *new_tile).into());
-                                return true;
-                            } else {
-                                // nothing changed
-                                return false;
-                            }
-                        });
+                    DrawMode::Move { start, end } => {
+                        if let (Some((tl, br)), Some(dest)) =
+                            (imp.selection.get(), this.move_dest(state, start, end))
+                        {
+                            let cells = move_cells(&state.tilemap_data(), (tl, br), dest);
+                            write_cells(state, &cells);
+                            imp.selection
+                                .set(Some((dest, (dest.0 + br.0 - tl.0, dest.1 + br.1 - tl.1))));
+                        }
+                        imp.tilemap_drawing.queue_draw();
                     }
-                    _ => {}
+                    ref drag => put_tiles(state, &drag.cells(), *imp.curr_tile.borrow()),
                 }
 
                 imp.curr_drag.replace(DrawMode::None);
             }
         ));
         self.imp().tilemap_drawing.add_controller(drag_event);
+
+        // right click picks up the tile under the cursor
+        let pick_event = GestureClick::builder()
+            .button(gdk::BUTTON_SECONDARY)
+            .build();
+        pick_event.connect_pressed(clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[weak]
+            state,
+            move |_, _, x, y| {
+                let Some(idx) = this.cursor_to_idx(&state, x, y) else {
+                    return;
+                };
+                let Some(tile) = state.tilemap_data().tiles.get(idx).copied() else {
+                    return;
+                };
+                this.pick_tile(&state, tile);
+            }
+        ));
+        self.imp().tilemap_drawing.add_controller(pick_event);
     }
 
-    fn cursor_to_idx(&self, x: f64, y: f64) -> Option<usize> {
+    // draw with tile from now on, updating the tile picker, palette picker and toggles to match
+    fn pick_tile(&self, state: &Window, tile: Tile) {
+        let imp = self.imp();
+        state.set_tileset_sel_idx(tile.tile_idx() as u32);
+        state.set_stamp(None);
+        state.set_curr_palette(tile.palette());
+        imp.flip_x_btn.set_active(tile.x_flip());
+        imp.flip_y_btn.set_active(tile.y_flip());
+        imp.priority_btn.set_active(tile.priority());
+        // palette bits are kept in 8bpp even though they are ignored
+        *imp.curr_tile.borrow_mut() = tile;
+    }
+
+    fn resize_drawing(&self, state: &Window) {
+        let imp = self.imp();
+        let (width, height) = {
+            let tilemap = state.tilemap_data();
+            (tilemap.width as f64, tilemap.height as f64)
+        };
+        let tile_w = TILE_W * self.tilemap_zoom().to_val();
+        imp.tilemap_drawing
+            .set_content_width((tile_w * width) as i32);
+        imp.tilemap_drawing
+            .set_content_height((tile_w * height) as i32);
+
+        imp.tilemap_drawing.queue_draw();
+    }
+
+    fn cursor_to_idx(&self, state: &Window, x: f64, y: f64) -> Option<usize> {
         let imp = self.imp();
         let tile_w = TILE_W * self.tilemap_zoom().to_val();
 
@@ -341,75 +480,395 @@ impl TilemapEditor {
             return None;
         }
 
+        let tilemap = state.tilemap_data();
         let (tile_x, tile_y) = (x / tile_w, y / tile_w);
-        if tile_x < 0.0 || tile_y < 0.0 || tile_x >= 32.0 || tile_y >= 32.0 {
+        if tile_x < 0.0
+            || tile_y < 0.0
+            || tile_x >= tilemap.width as f64
+            || tile_y >= tilemap.height as f64
+        {
             // cursor position outside of tilemap drawing
             return None;
         }
 
-        let new_idx = tile_y.floor() as usize * 32 + tile_x.floor() as usize;
+        let new_idx = tile_y.floor() as usize * tilemap.width + tile_x.floor() as usize;
         Some(new_idx)
     }
 
     fn draw_tilemap(&self, cr: &gtk::cairo::Context, state: &Window) {
-        let tileset = state.tileset_data();
         let curr_drag = self.imp().curr_drag.borrow();
+        let curr_tile = *self.imp().curr_tile.borrow();
+        let tilemap = state.tilemap_data();
 
         // fallback color
         cr.set_source_rgb(0.4, 0.4, 0.4);
         let _ = cr.paint();
 
-        let curr_tile = self.imp().curr_tile.borrow();
-        for (i, tile) in state.tilemap_data().0.iter().enumerate() {
-            let ix = i % 32;
-            let iy = i / 32;
-            let x_offset = ix as f64 * TILE_W;
-            let y_offset = iy as f64 * TILE_W;
-
-            // decide which tile to draw
-            let tile = if curr_drag.idx_in_range(ix, iy) {
-                &curr_tile
-            } else {
-                tile
-            };
-
-            let _ = cr.save();
-            cr.translate(x_offset, y_offset);
-            if tile.x_flip() {
-                cr.translate(TILE_W, 0.0);
-                cr.scale(-1.0, 1.0);
-            }
-            if tile.y_flip() {
-                cr.translate(0.0, TILE_W);
-                cr.scale(1.0, -1.0);
-            }
-
-            let idx = tile.tile_idx().into();
-            match state.tile_size() {
-                TileSize::Eight => {
-                    tileset.draw_tile(idx, cr, state, Some(tile.palette()));
-                }
-                TileSize::Sixteen => {
-                    cr.scale(0.5, 0.5);
-                    tileset.draw_tile(idx, cr, state, Some(tile.palette()));
-                    cr.translate(TILE_W, 0.0);
-                    tileset.draw_tile(idx + 1, cr, state, Some(tile.palette()));
-                    cr.translate(-TILE_W, TILE_W);
-                    tileset.draw_tile(idx + 16, cr, state, Some(tile.palette()));
-                    cr.translate(TILE_W, 0.0);
-                    tileset.draw_tile(idx + 17, cr, state, Some(tile.palette()));
+        // draw curr_tile where the current stroke goes
+        let stroke: HashSet<_> = curr_drag.cells().into_iter().collect();
+        let mut preview = Tilemap {
+            tiles: tilemap
+                .tiles
+                .iter()
+                .enumerate()
+                .map(|(i, tile)| {
+                    if stroke.contains(&(i % tilemap.width, i / tilemap.width)) {
+                        curr_tile
+                    } else {
+                        *tile
+                    }
+                })
+                .collect(),
+            width: tilemap.width,
+            height: tilemap.height,
+        };
+        if let Some(cells) = self.stamp_cells(state, &curr_drag) {
+            for ((x, y), tile) in cells {
+                preview.tiles[y * preview.width + x] = tile;
+            }
+        }
+        let selection = self.imp().selection.get();
+        let outline = match (&*curr_drag, selection) {
+            (DrawMode::Select { start, end }, _) => Some((
+                (start.0.min(end.0), start.1.min(end.1)),
+                (start.0.max(end.0), start.1.max(end.1)),
+            )),
+            // show the tiles where they would be moved to
+            (DrawMode::Move { start, end }, Some((tl, br))) => {
+                match self.move_dest(state, *start, *end) {
+                    Some(dest) => {
+                        for ((x, y), tile) in move_cells(&tilemap, (tl, br), dest) {
+                            preview.tiles[y * preview.width + x] = tile;
+                        }
+                        Some((dest, (dest.0 + br.0 - tl.0, dest.1 + br.1 - tl.1)))
+                    }
+                    None => selection,
                 }
             }
-            let _ = cr.restore();
+            _ => selection,
+        };
+
+        let options = state.render_options(state.tile_bpp());
+        let image = render_tilemap(
+            &state.tileset_data(),
+            &state.palette_data(),
+            &preview,
+            options,
+        );
+        // one tile is TILE_W wide no matter the tile size
+        paint_image(cr, &image, TILE_W / options.tile_size as usize as f64);
+
+        // dashed outline around the selection
+        if let Some((tl, br)) = outline {
+            let (x, y) = (tl.0 as f64 * TILE_W, tl.1 as f64 * TILE_W);
+            let (w, h) = (
+                (br.0 - tl.0 + 1) as f64 * TILE_W,
+                (br.1 - tl.1 + 1) as f64 * TILE_W,
+            );
+            cr.set_line_width(2.0);
+            for (offset, c) in [(0.0, 1.0), (4.0, 0.0)] {
+                cr.rectangle(x + 1.0, y + 1.0, w - 2.0, h - 2.0);
+                cr.set_dash(&[4.0, 4.0], offset);
+                cr.set_source_rgb(c, c, c);
+                let _ = cr.stroke();
+            }
+        }
+    }
+
+    // contiguous cells matching the cell at idx, as set by the fill match dropdown
+    fn fill_region(&self, state: &Window, idx: (usize, usize)) -> HashSet<(usize, usize)> {
+        let tilemap = state.tilemap_data();
+        let Some(target) = tilemap.get(idx) else {
+            return HashSet::new();
+        };
+        let fill_match = FillMatch::iter()
+            .nth(self.imp().fill_match_select.selected() as usize)
+            .unwrap_or_default();
+        let matches = |tile: Tile| match fill_match {
+            FillMatch::TileIdx => tile.tile_idx() == target.tile_idx(),
+            FillMatch::Tile => tile == target,
+        };
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![idx];
+        while let Some((x, y)) = stack.pop() {
+            if !tilemap.get((x, y)).is_some_and(matches) || !visited.insert((x, y)) {
+                continue;
+            }
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if x + 1 < tilemap.width {
+                stack.push((x + 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+            if y + 1 < tilemap.height {
+                stack.push((x, y + 1));
+            }
+        }
+        visited
+    }
+
+    // stamp with the flip buttons applied, None if the pen and rect fill draw curr_tile
+    fn stamp_brush(&self, state: &Window) -> Option<TileBlock> {
+        let imp = self.imp();
+        state
+            .stamp()
+            .map(|stamp| stamp.flipped(imp.flip_x_btn.is_active(), imp.flip_y_btn.is_active()))
+    }
+
+    // cells the pen covers with the cursor at idx
+    fn pen_cells(&self, state: &Window, idx: (usize, usize)) -> Vec<(usize, usize)> {
+        match self.stamp_brush(state) {
+            Some(stamp) => {
+                let tilemap = state.tilemap_data();
+                stamp.copy_covering(
+                    self.imp().pen_origin.get(),
+                    idx,
+                    (tilemap.width, tilemap.height),
+                )
+            }
+            None => vec![idx],
+        }
+    }
+
+    // tiles the stamp puts down for a stroke, None if there is no stamp
+    fn stamp_cells(
+        &self,
+        state: &Window,
+        drag: &DrawMode,
+    ) -> Option<HashMap<(usize, usize), Tile>> {
+        let stamp = self.stamp_brush(state)?;
+        let origin = match drag {
+            DrawMode::Pen(_) => self.imp().pen_origin.get(),
+            // the pattern starts at the top left corner of rectangles and the start of lines
+            DrawMode::RectFill { start, end } | DrawMode::Rect { start, end } => {
+                (start.0.min(end.0), start.1.min(end.1))
+            }
+            DrawMode::Line { start, .. } => *start,
+            _ => return None,
+        };
+        Some(
+            drag.cells()
+                .into_iter()
+                .map(|cell| (cell, stamp.tile_from(origin, cell)))
+                .collect(),
+        )
+    }
+
+    // copy selected tiles to the clipboard, false if nothing is selected
+    fn copy_selection(&self, state: &Window) -> bool {
+        let imp = self.imp();
+        let Some((tl, br)) = imp.selection.get() else {
+            return false;
+        };
+        let block = state.tilemap_data().copy_rect(tl, br);
+        println!("copy {}x{} tiles", block.width, block.height);
+        imp.clipboard.replace(Some(block));
+        true
+    }
+
+    fn cut_selection(&self, state: &Window) {
+        if !self.copy_selection(state) {
+            return;
+        }
+        let Some((tl, br)) = self.imp().selection.get() else {
+            return;
+        };
+        let mut cells = HashMap::new();
+        for y in tl.1..=br.1 {
+            for x in tl.0..=br.0 {
+                cells.insert((x, y), Tile::default());
+            }
+        }
+        write_cells(state, &cells);
+    }
+
+    // paste at the top left corner of the selection, or of the visible part of the tilemap
+    fn paste(&self, state: &Window) {
+        let imp = self.imp();
+        let Some(block) = imp.clipboard.borrow().clone() else {
+            return;
+        };
+        let (width, height) = {
+            let tilemap = state.tilemap_data();
+            (tilemap.width, tilemap.height)
+        };
+        let dest = match imp.selection.get() {
+            Some((tl, _)) => tl,
+            None => {
+                let tile_w = TILE_W * self.tilemap_zoom().to_val();
+                let scroll = &imp.tilemap_scroll;
+                (
+                    ((scroll.hadjustment().value() / tile_w) as usize).min(width - 1),
+                    ((scroll.vadjustment().value() / tile_w) as usize).min(height - 1),
+                )
+            }
+        };
+        write_cells(state, &block.cells_at(dest).collect());
+
+        // select the pasted tiles so they can be dragged into place
+        let br = (
+            (dest.0 + block.width - 1).min(width - 1),
+            (dest.1 + block.height - 1).min(height - 1),
+        );
+        imp.select_btn.set_active(true);
+        imp.selection.set(Some((dest, br)));
+        imp.tilemap_drawing.queue_draw();
+    }
+
+    fn select_actions(&self, state: &Window) -> [ActionEntry<SimpleActionGroup>; 5] {
+        let action_copy = ActionEntry::builder("copy")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    this.copy_selection(&state);
+                }
+            ))
+            .build();
+
+        let action_cut = ActionEntry::builder("cut")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    this.cut_selection(&state);
+                }
+            ))
+            .build();
+
+        let action_paste = ActionEntry::builder("paste")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    this.paste(&state);
+                }
+            ))
+            .build();
+
+        let action_select_all = ActionEntry::builder("selectall")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    let imp = this.imp();
+                    let (width, height) = {
+                        let tilemap = state.tilemap_data();
+                        (tilemap.width, tilemap.height)
+                    };
+                    imp.select_btn.set_active(true);
+                    imp.selection.set(Some(((0, 0), (width - 1, height - 1))));
+                    imp.tilemap_drawing.queue_draw();
+                }
+            ))
+            .build();
+
+        // selected tiles become the stamp for the pen and rect fill
+        let action_stamp = ActionEntry::builder("stamp")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                self,
+                #[weak]
+                state,
+                move |_, _, _| {
+                    let imp = this.imp();
+                    let Some((tl, br)) = imp.selection.get() else {
+                        return;
+                    };
+                    let stamp = state.tilemap_data().copy_rect(tl, br);
+                    println!("stamp: {}x{} tiles", stamp.width, stamp.height);
+                    state.set_stamp(Some(stamp));
+                    imp.pen_draw_btn.set_active(true);
+                }
+            ))
+            .build();
+
+        [
+            action_copy,
+            action_cut,
+            action_paste,
+            action_select_all,
+            action_stamp,
+        ]
+    }
+
+    // new top left corner of the selection when dragged from start to end,
+    // kept inside the tilemap, None if it doesn't move
+    fn move_dest(
+        &self,
+        state: &Window,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let (tl, br) = self.imp().selection.get()?;
+        let tilemap = state.tilemap_data();
+        let dx = (end.0 as isize - start.0 as isize)
+            .clamp(-(tl.0 as isize), (tilemap.width - 1 - br.0) as isize);
+        let dy = (end.1 as isize - start.1 as isize)
+            .clamp(-(tl.1 as isize), (tilemap.height - 1 - br.1) as isize);
+        if dx == 0 && dy == 0 {
+            return None;
         }
+        Some(((tl.0 as isize + dx) as usize, (tl.1 as isize + dy) as usize))
     }
 
     fn file_actions(&self, state: &Window) {
+        let action_new = ActionEntry::builder("new")
+            .parameter_type(Some(&String::static_variant_type()))
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, parameter| {
+                    // parse tilemap size parameter
+                    let Some(size) = parameter else { return };
+                    let size = size
+                        .get::<String>()
+                        .expect("parameter should have type String");
+                    let size = TilemapSize::from_str(&size).expect("invalid tilemap size");
+
+                    if state.tilemap_dirty() {
+                        unsaved_tilemap_dialog(
+                            &state,
+                            clone!(
+                                #[weak]
+                                state,
+                                move || {
+                                    new_file(&state, size);
+                                }
+                            ),
+                        );
+                    } else {
+                        new_file(&state, size);
+                    }
+                }
+            ))
+            .build();
+
         let action_open = ActionEntry::builder("open")
+            .parameter_type(Some(&String::static_variant_type()))
             .activate(clone!(
                 #[weak]
                 state,
-                move |_, _, _| {
+                move |_, _, parameter| {
+                    // parse tilemap size parameter
+                    let Some(size) = parameter else { return };
+                    let size = size
+                        .get::<String>()
+                        .expect("parameter should have type String");
+                    let size = TilemapSize::from_str(&size).expect("invalid tilemap size");
+
                     file_open_dialog(state.clone(), move |path| {
                         if state.tilemap_dirty() {
                             unsaved_tilemap_dialog(
@@ -418,18 +877,76 @@ impl TilemapEditor {
                                     #[weak]
                                     state,
                                     move || {
-                                        open_file(&state, path.clone());
+                                        if let Err(e) = open_file(&state, path.clone(), size) {
+                                            eprintln!("Error: {e}");
+                                        }
                                     }
                                 ),
                             );
-                        } else {
-                            open_file(&state, path);
+                        } else if let Err(e) = open_file(&state, path, size) {
+                            eprintln!("Error: {e}");
                         }
                     });
                 }
             ))
             .build();
 
+        let action_open_rom = ActionEntry::builder("openrom")
+            .parameter_type(Some(&String::static_variant_type()))
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, parameter| {
+                    // parse tilemap size parameter
+                    let Some(size) = parameter else { return };
+                    let size = size
+                        .get::<String>()
+                        .expect("parameter should have type String");
+                    let size = TilemapSize::from_str(&size).expect("invalid tilemap size");
+                    let (width, height) = size.dims();
+
+                    file_open_dialog(state.clone(), move |path| {
+                        rom_address_dialog(
+                            &state.clone(),
+                            width * height * 2,
+                            clone!(
+                                #[weak]
+                                state,
+                                move |kind, addr, len| {
+                                    let path = path.clone();
+                                    if state.tilemap_dirty() {
+                                        unsaved_tilemap_dialog(
+                                            &state,
+                                            clone!(
+                                                #[weak]
+                                                state,
+                                                move || {
+                                                    if let Err(e) = open_rom(
+                                                        &state,
+                                                        path.clone(),
+                                                        size,
+                                                        kind,
+                                                        addr,
+                                                        len,
+                                                    ) {
+                                                        eprintln!("Error: {e}");
+                                                    }
+                                                }
+                                            ),
+                                        );
+                                    } else if let Err(e) =
+                                        open_rom(&state, path, size, kind, addr, len)
+                                    {
+                                        eprintln!("Error: {e}");
+                                    }
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+
         let action_reload = ActionEntry::builder("reload")
             .activate(clone!(
                 #[weak]
@@ -440,19 +957,28 @@ impl TilemapEditor {
                         return;
                     };
 
-                    if state.tilemap_dirty() {
-                        unsaved_tilemap_dialog(
-                            &state,
-                            clone!(
-                                #[weak]
-                                state,
-                                move || {
-                                    open_file(&state, file.clone());
+                    let reload = clone!(
+                        #[weak]
+                        state,
+                        move || {
+                            let size = state.tilemap_data().size().unwrap_or_default();
+                            if state.tilemap_mode7() {
+                                if let Err(e) = open_mode7_tilemap(&state, file.clone()) {
+                                    eprintln!("Error: {e}");
                                 }
-                            ),
-                        );
+                            } else if let Some(region) =
+                                state.tilemap_rom().filter(|r| r.path == file)
+                            {
+                                reload_rom(&state, region, size);
+                            } else if let Err(e) = open_file(&state, file.clone(), size) {
+                                eprintln!("Error: {e}");
+                            }
+                        }
+                    );
+                    if state.tilemap_dirty() {
+                        unsaved_tilemap_dialog(&state, reload);
                     } else {
-                        open_file(&state, file.clone());
+                        reload();
                     }
                 }
             ))
@@ -466,7 +992,9 @@ impl TilemapEditor {
                     let Some(filepath) = state.tilemap_file() else {
                         return;
                     };
-                    save_file(&state, filepath);
+                    if let Err(e) = save_file(&state, filepath) {
+                        eprintln!("Error saving file: {e}");
+                    }
                 }
             ))
             .build();
@@ -477,14 +1005,153 @@ impl TilemapEditor {
                 state,
                 move |_, _, _| {
                     file_save_dialog(&state.clone(), move |_, filepath| {
-                        save_file(&state, filepath);
+                        if let Err(e) = save_file(&state, filepath) {
+                            eprintln!("Error saving file: {e}");
+                        }
+                    });
+                }
+            ))
+            .build();
+
+        let action_import_png = ActionEntry::builder("importpng")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    png_open_dialog(state.clone(), move |path| {
+                        unsaved_all_dialog(
+                            &state,
+                            clone!(
+                                #[weak]
+                                state,
+                                move || {
+                                    import_png(&state, path.clone());
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+
+        let action_open_scene = ActionEntry::builder("openscene")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog_titled(state.clone(), "Open VRAM Dump", move |vram_path| {
+                        file_open_dialog_titled(
+                            state.clone(),
+                            "Open CGRAM Dump",
+                            clone!(
+                                #[weak]
+                                state,
+                                move |cgram_path| {
+                                    let vram_path = vram_path.clone();
+                                    scene_dialog(&state.clone(), move |scene| {
+                                        let (vram_path, cgram_path) =
+                                            (vram_path.clone(), cgram_path.clone());
+                                        unsaved_all_dialog(
+                                            &state,
+                                            clone!(
+                                                #[weak]
+                                                state,
+                                                move || {
+                                                    open_scene(
+                                                        &state,
+                                                        vram_path.clone(),
+                                                        cgram_path.clone(),
+                                                        scene,
+                                                    );
+                                                }
+                                            ),
+                                        );
+                                    });
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+
+        let action_export_header = ActionEntry::builder("exportheader")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    c_header_save_dialog(
+                        &state.clone(),
+                        clone!(
+                            #[weak]
+                            state,
+                            move |header, filepath| {
+                                export_header(&state, header, filepath);
+                            }
+                        ),
+                    );
+                }
+            ))
+            .build();
+
+        let action_export_source = ActionEntry::builder("exportsource")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    source_save_dialog(&state.clone(), move |source, filepath| {
+                        export_source(&state, source, filepath);
                     });
                 }
             ))
             .build();
 
+        let action_export_png = ActionEntry::builder("exportpng")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    png_save_dialog(
+                        &state.clone(),
+                        &[(
+                            "color0",
+                            "Color 0",
+                            &[
+                                ("transparent", "Transparent"),
+                                ("backdrop", "Backdrop color"),
+                            ],
+                        )],
+                        move |d, filepath| {
+                            let transparent =
+                                d.choice("color0").is_some_and(|c| c == "transparent");
+                            export_png(&state, filepath, transparent);
+                        },
+                    );
+                }
+            ))
+            .build();
+
         let actions = SimpleActionGroup::new();
-        actions.add_action_entries([action_open, action_reload, action_save, action_save_as]);
+        actions.add_action_entries([
+            action_new,
+            action_open,
+            action_open_rom,
+            action_reload,
+            action_save,
+            action_save_as,
+            action_import_png,
+            action_open_scene,
+            action_export_png,
+            action_export_source,
+            action_export_header,
+        ]);
+        actions.add_action_entries(self.select_actions(state));
+        // compression used when opening and saving
+        actions.add_action(&PropertyAction::new(
+            "compression",
+            state,
+            "tilemap_compression",
+        ));
 
         // bind file to action
         let reload = actions.lookup_action("reload").unwrap();
@@ -502,4 +1169,103 @@ impl TilemapEditor {
 
         state.insert_action_group("tilemap", Some(&actions));
     }
+
+    fn mode7_actions(&self, state: &Window) {
+        let action_open = ActionEntry::builder("open")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog(state.clone(), move |path| {
+                        unsaved_mode7_dialog(
+                            &state,
+                            clone!(
+                                #[weak]
+                                state,
+                                move || {
+                                    open_mode7_file(&state, path.clone());
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+
+        let action_save_as = ActionEntry::builder("saveas")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_save_dialog(&state.clone(), move |_, filepath| {
+                        save_mode7_file(&state, filepath);
+                    });
+                }
+            ))
+            .build();
+
+        let action_open_map = ActionEntry::builder("openmap")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog(state.clone(), move |path| {
+                        if state.tilemap_dirty() {
+                            unsaved_tilemap_dialog(
+                                &state,
+                                clone!(
+                                    #[weak]
+                                    state,
+                                    move || {
+                                        if let Err(e) = open_mode7_tilemap(&state, path.clone()) {
+                                            eprintln!("Error: {e}");
+                                        }
+                                    }
+                                ),
+                            );
+                        } else if let Err(e) = open_mode7_tilemap(&state, path) {
+                            eprintln!("Error: {e}");
+                        }
+                    });
+                }
+            ))
+            .build();
+
+        let action_open_chr = ActionEntry::builder("openchr")
+            .activate(clone!(
+                #[weak]
+                state,
+                move |_, _, _| {
+                    file_open_dialog(state.clone(), move |path| {
+                        if state.tileset_dirty() {
+                            unsaved_tileset_dialog(
+                                &state,
+                                clone!(
+                                    #[weak]
+                                    state,
+                                    move || {
+                                        if let Err(e) = open_mode7_tileset(&state, path.clone()) {
+                                            eprintln!("Error: {e}");
+                                        }
+                                    }
+                                ),
+                            );
+                        } else if let Err(e) = open_mode7_tileset(&state, path) {
+                            eprintln!("Error: {e}");
+                        }
+                    });
+                }
+            ))
+            .build();
+
+        let actions = SimpleActionGroup::new();
+        actions.add_action_entries([
+            action_open,
+            action_save_as,
+            action_open_map,
+            action_open_chr,
+        ]);
+
+        state.insert_action_group("mode7", Some(&actions));
+    }
 }
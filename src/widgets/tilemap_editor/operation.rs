@@ -13,7 +13,7 @@ impl UndoRedo for ChangeTilemapTile {
     fn undo(&self, state: &Window) {
         state.modify_tilemap_data(|tilemap| {
             for ((x, y), tile) in &self.before {
-                tilemap.0[y * 32 + x] = *tile;
+                tilemap.tiles[y * tilemap.width + x] = *tile;
             }
             true
         });
@@ -22,7 +22,7 @@ impl UndoRedo for ChangeTilemapTile {
     fn redo(&self, state: &Window) {
         state.modify_tilemap_data(|tilemap| {
             for (x, y) in self.before.keys() {
-                tilemap.0[y * 32 + x] = self.after;
+                tilemap.tiles[y * tilemap.width + x] = self.after;
             }
             true
         });
@@ -34,3 +34,37 @@ impl ChangeTilemapTile {
         Self { before, after }
     }
 }
+
+// different new tile for each cell, for paste and move
+pub struct ChangeTilemapCells {
+    before: HashMap<(usize, usize), Tile>,
+    after: HashMap<(usize, usize), Tile>,
+}
+
+impl UndoRedo for ChangeTilemapCells {
+    fn undo(&self, state: &Window) {
+        Self::write(state, &self.before);
+    }
+
+    fn redo(&self, state: &Window) {
+        Self::write(state, &self.after);
+    }
+}
+
+impl ChangeTilemapCells {
+    pub fn new(
+        before: HashMap<(usize, usize), Tile>,
+        after: HashMap<(usize, usize), Tile>,
+    ) -> Self {
+        Self { before, after }
+    }
+
+    fn write(state: &Window, cells: &HashMap<(usize, usize), Tile>) {
+        state.modify_tilemap_data(|tilemap| {
+            for ((x, y), tile) in cells {
+                tilemap.tiles[y * tilemap.width + x] = *tile;
+            }
+            true
+        });
+    }
+}
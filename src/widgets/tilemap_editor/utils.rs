@@ -1,18 +1,83 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::path::PathBuf;
 
 use gtk::glib::{self, clone};
 
-use crate::data::tilemap::Tilemap;
+use crate::data::{
+    image,
+    list_items::{Bpp, TileSize, TilemapSize},
+    mode7,
+    palette::Palette,
+    render::{render_tilemap, ColorZero, RenderOptions},
+    rom::{AddressKind, RomRegion},
+    source::{CHeader, Source},
+    tilemap::{Tile, Tilemap},
+    tiles::Tileset,
+    vram::Scene,
+};
 use crate::utils::*;
-use crate::widgets::window::Window;
+use crate::widgets::tilemap_editor::operation::{ChangeTilemapCells, ChangeTilemapTile};
+use crate::widgets::{
+    palette_picker::utils::unsaved_palette_dialog, tile_picker::utils::unsaved_tileset_dialog,
+    window::Window,
+};
 
-pub fn open_file(state: &Window, filepath: PathBuf) {
-    match Tilemap::from_file(&filepath) {
+pub fn new_file(state: &Window, size: TilemapSize) {
+    println!("new {size} tilemap");
+    let (width, height) = size.dims();
+    state.set_tilemap_mode7(false);
+    state.set_tilemap_data(Tilemap::new(width, height));
+    state.set_tilemap_file(None::<PathBuf>);
+    state.mark_tilemap_clean();
+    state.clear_history();
+}
+
+pub fn open_file(state: &Window, filepath: PathBuf, size: TilemapSize) -> std::io::Result<()> {
+    let content = state.tilemap_compression().read_file(&filepath)?;
+    let res = Tilemap::from_bytes(&content, size)?;
+    println!("load tilemap: {filepath:?}");
+    state.set_tilemap_mode7(false);
+    state.set_tilemap_data(res);
+    state.set_tilemap_file(Some(filepath));
+    state.set_tilemap_rom(None);
+    state.mark_tilemap_clean();
+    state.clear_history();
+    Ok(())
+}
+
+pub fn open_rom(
+    state: &Window,
+    filepath: PathBuf,
+    size: TilemapSize,
+    kind: AddressKind,
+    addr: usize,
+    len: usize,
+) -> std::io::Result<()> {
+    let (region, content) =
+        RomRegion::read(&filepath, kind, addr, len, state.tilemap_compression())?;
+    let res = Tilemap::from_bytes(&content, size)?;
+    println!(
+        "load tilemap from rom: {filepath:?} at {:#X}",
+        region.offset
+    );
+    state.set_tilemap_mode7(false);
+    state.set_tilemap_data(res);
+    state.set_tilemap_file(Some(filepath));
+    state.set_tilemap_rom(Some(region));
+    state.mark_tilemap_clean();
+    state.clear_history();
+    Ok(())
+}
+
+pub fn reload_rom(state: &Window, region: RomRegion, size: TilemapSize) {
+    match region
+        .reload(state.tilemap_compression())
+        .and_then(|content| Tilemap::from_bytes(&content, size))
+    {
         Ok(res) => {
-            println!("load tilemap: {filepath:?}");
+            println!("reload tilemap from rom: {:?}", region.path);
             state.set_tilemap_data(res);
-            state.set_tilemap_file(Some(filepath));
             state.mark_tilemap_clean();
             state.clear_history();
         }
@@ -20,16 +85,36 @@ pub fn open_file(state: &Window, filepath: PathBuf) {
     }
 }
 
-pub fn save_file(state: &Window, filepath: PathBuf) {
-    match File::create(filepath.clone()) {
-        Ok(f) => {
-            let _ = state.tilemap_data().write_to_file(&f);
-            println!("save tilemap: {filepath:?}");
-            state.set_tilemap_file(Some(filepath));
-            state.mark_tilemap_clean();
+pub fn save_file(state: &Window, filepath: PathBuf) -> std::io::Result<()> {
+    // write back into the rom it was opened from
+    if let Some(region) = state.tilemap_rom().filter(|r| r.path == filepath) {
+        // the rom can only be patched, never replaced by a tilemap file
+        if state.tilemap_mode7() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "can't save a mode 7 tilemap into a rom",
+            ));
         }
-        Err(e) => eprintln!("Error saving file: {e}"),
+        let content = state.tilemap_data().to_bytes();
+        region.patch(&content, state.tilemap_compression())?;
+        println!("patch tilemap: {filepath:?} at {:#X}", region.offset);
+        state.mark_tilemap_clean();
+        return Ok(());
     }
+
+    // check mode 7 entries before the file is overwritten
+    let content = if state.tilemap_mode7() {
+        state.tilemap_data().to_bytes_mode7()?
+    } else {
+        state
+            .tilemap_compression()
+            .compress(&state.tilemap_data().to_bytes())
+    };
+    std::fs::write(&filepath, content)?;
+    println!("save tilemap: {filepath:?}");
+    state.set_tilemap_file(Some(filepath));
+    state.mark_tilemap_clean();
+    Ok(())
 }
 
 pub fn unsaved_tilemap_dialog(state: &Window, after: impl Fn() + Clone + 'static) {
@@ -50,13 +135,17 @@ pub fn unsaved_tilemap_dialog(state: &Window, after: impl Fn() + Clone + 'static
                 let after1 = after.clone();
                 if let Some(filepath) = state.tilemap_file() {
                     // save to palette_file
-                    save_file(&state, filepath.clone());
-                    after1();
+                    match save_file(&state, filepath.clone()) {
+                        Ok(_) => after1(),
+                        Err(e) => eprintln!("Error saving file: {e}"),
+                    }
                 } else {
                     // save to new file
                     file_save_dialog(&state.clone(), move |_, filepath| {
-                        save_file(&state, filepath);
-                        after1();
+                        match save_file(&state, filepath) {
+                            Ok(_) => after1(),
+                            Err(e) => eprintln!("Error saving file: {e}"),
+                        }
                     });
                 }
             }
@@ -72,3 +161,274 @@ pub fn unsaved_tilemap_dialog(state: &Window, after: impl Fn() + Clone + 'static
         ),
     );
 }
+
+// check both tilemap and tileset for unsaved data
+pub fn unsaved_mode7_dialog(state: &Window, after: impl Fn() + Clone + 'static) {
+    let after_tilemap = clone!(
+        #[weak]
+        state,
+        move || {
+            if state.tileset_dirty() {
+                unsaved_tileset_dialog(&state, after.clone());
+            } else {
+                after();
+            }
+        }
+    );
+    if state.tilemap_dirty() {
+        unsaved_tilemap_dialog(state, after_tilemap);
+    } else {
+        after_tilemap();
+    }
+}
+
+// interleaved tilemap and character data
+pub fn open_mode7_file(state: &Window, filepath: PathBuf) {
+    match mode7::from_file(&filepath) {
+        Ok((tilemap, tileset)) => {
+            println!("load mode 7 data: {filepath:?}");
+            state.set_tileset_mode7(true);
+            state.set_tilemap_mode7(true);
+            state.set_tile_bpp(Bpp::Eight);
+            state.set_tileset_data(tileset);
+            state.set_tileset_sel_idx(0);
+            state.set_tilemap_data(tilemap);
+            // tileset and tilemap can't be saved separately to the same file
+            state.set_tileset_file(None::<PathBuf>);
+            state.set_tilemap_file(None::<PathBuf>);
+            state.mark_tileset_clean();
+            state.mark_tilemap_clean();
+            state.clear_history();
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+pub fn save_mode7_file(state: &Window, filepath: PathBuf) {
+    // don't replace a rom the tileset or tilemap was opened from
+    let rom = [state.tileset_rom(), state.tilemap_rom()];
+    if rom.iter().flatten().any(|r| r.path == filepath) {
+        eprintln!("Error saving file: can't save mode 7 data into a rom");
+        return;
+    }
+    match mode7::to_bytes(&state.tilemap_data(), &state.tileset_data())
+        .and_then(|content| std::fs::write(&filepath, content))
+    {
+        Ok(_) => {
+            println!("save mode 7 data: {filepath:?}");
+            state.mark_tileset_clean();
+            state.mark_tilemap_clean();
+        }
+        Err(e) => eprintln!("Error saving file: {e}"),
+    }
+}
+
+pub fn open_mode7_tilemap(state: &Window, filepath: PathBuf) -> std::io::Result<()> {
+    let res = Tilemap::from_file_mode7(&filepath)?;
+    println!("load mode 7 tilemap: {filepath:?}");
+    state.set_tilemap_mode7(true);
+    state.set_tilemap_data(res);
+    state.set_tilemap_file(Some(filepath));
+    state.set_tilemap_rom(None);
+    state.mark_tilemap_clean();
+    state.clear_history();
+    Ok(())
+}
+
+pub fn open_mode7_tileset(state: &Window, filepath: PathBuf) -> std::io::Result<()> {
+    let res = Tileset::from_file_mode7(&filepath)?;
+    println!("load mode 7 tileset: {filepath:?}");
+    state.set_tileset_mode7(true);
+    state.set_tile_bpp(Bpp::Eight);
+    state.set_tileset_data(res);
+    state.set_tileset_sel_idx(0);
+    state.set_tileset_file(Some(filepath));
+    state.set_tileset_rom(None);
+    state.mark_tileset_clean();
+    state.clear_history();
+    Ok(())
+}
+
+// check palette, tileset and tilemap for unsaved data
+pub fn unsaved_all_dialog(state: &Window, after: impl Fn() + Clone + 'static) {
+    let after_palette = clone!(
+        #[weak]
+        state,
+        move || {
+            if state.tileset_dirty() || state.tilemap_dirty() {
+                unsaved_mode7_dialog(&state, after.clone());
+            } else {
+                after();
+            }
+        }
+    );
+    if state.palette_dirty() {
+        unsaved_palette_dialog(state, after_palette);
+    } else {
+        after_palette();
+    }
+}
+
+// replace palette, tileset and tilemap with data cut from an image
+pub fn import_png(state: &Window, filepath: PathBuf) {
+    let palette = Palette(state.palette_data().0);
+    match image::import_png(
+        &filepath,
+        state.tile_bpp(),
+        state.palette_base() as usize,
+        palette,
+    ) {
+        Ok((tileset, tilemap, palette)) => {
+            println!("import png: {filepath:?}, {} unique tiles", tileset.0.len());
+            state.set_tileset_mode7(false);
+            state.set_tilemap_mode7(false);
+            state.set_palette_data(palette);
+            state.set_tileset_data(tileset);
+            state.set_tileset_sel_idx(0);
+            state.set_tilemap_data(tilemap);
+            // imported data has not been saved anywhere yet
+            state.set_palette_file(None::<PathBuf>);
+            state.set_tileset_file(None::<PathBuf>);
+            state.set_tilemap_file(None::<PathBuf>);
+            state.clear_history();
+            state.mark_palette_dirty();
+            state.mark_tileset_dirty();
+            state.mark_tilemap_dirty();
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+// replace palette, tileset and tilemap with what a layer shows in VRAM and CGRAM dumps
+pub fn open_scene(state: &Window, vram_path: PathBuf, cgram_path: PathBuf, scene: Scene) {
+    match scene.read_files(&vram_path, &cgram_path) {
+        Ok((palette, tileset, tilemap)) => {
+            println!("load {} scene: {vram_path:?}, {cgram_path:?}", scene.layer);
+            let bpp = scene.layer.bpp();
+            state.set_tileset_mode7(scene.layer.is_mode7());
+            state.set_tilemap_mode7(scene.layer.is_mode7());
+            state.set_tile_bpp(bpp);
+            if bpp == Bpp::Two {
+                state.set_bg_mode(scene.layer.bg_mode_two());
+            }
+            state.set_palette_data(palette);
+            state.set_tileset_data(tileset);
+            state.set_tileset_sel_idx(0);
+            state.set_tilemap_data(tilemap);
+            // dumps can't be saved back
+            state.set_palette_file(None::<PathBuf>);
+            state.set_tileset_file(None::<PathBuf>);
+            state.set_tilemap_file(None::<PathBuf>);
+            state.mark_palette_clean();
+            state.mark_tileset_clean();
+            state.mark_tilemap_clean();
+            state.clear_history();
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+pub fn export_png(state: &Window, filepath: PathBuf, transparent: bool) {
+    // mode 7 only uses 8x8 tiles
+    let tile_size = if state.tilemap_mode7() {
+        TileSize::Eight
+    } else {
+        state.tile_size()
+    };
+    let options = RenderOptions {
+        bpp: state.tile_bpp(),
+        palette_base: state.palette_base() as usize,
+        tile_size,
+        color_zero: if transparent {
+            ColorZero::Transparent
+        } else {
+            ColorZero::Backdrop
+        },
+        mark_missing: false,
+    };
+    let image = render_tilemap(
+        &state.tileset_data(),
+        &state.palette_data(),
+        &state.tilemap_data(),
+        options,
+    );
+    match File::create(filepath.clone()).and_then(|f| image.write_png(&f)) {
+        Ok(_) => println!("export tilemap image: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting image: {e}"),
+    }
+}
+
+pub fn export_source(state: &Window, source: Source, filepath: PathBuf) {
+    match File::create(filepath.clone())
+        .and_then(|f| source.write_tilemap(&state.tilemap_data(), state.tilemap_mode7(), &f))
+    {
+        Ok(_) => println!("export tilemap source: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting source: {e}"),
+    }
+}
+
+pub fn export_header(state: &Window, header: CHeader, filepath: PathBuf) {
+    match File::create(filepath.clone())
+        .and_then(|f| header.write_tilemap(&state.tilemap_data(), state.tilemap_mode7(), &f))
+    {
+        Ok(_) => println!("export tilemap header: {filepath:?}"),
+        Err(e) => eprintln!("Error exporting header: {e}"),
+    }
+}
+
+// set cells to new_tile as one undo operation
+pub fn put_tiles(state: &Window, cells: &[(usize, usize)], new_tile: Tile) {
+    state.modify_tilemap_data(|tilemap| {
+        let mut before = HashMap::new();
+        for &(x, y) in cells {
+            if let Some(old) = tilemap.get((x, y)).filter(|old| *old != new_tile) {
+                before.insert((x, y), old);
+                tilemap.tiles[y * tilemap.width + x] = new_tile;
+            }
+        }
+        if before.is_empty() {
+            // nothing changed
+            return false;
+        }
+        state.push_op(ChangeTilemapTile::new(before, new_tile).into());
+        true
+    });
+}
+
+// write a different tile to each cell as one undo operation, cells outside the tilemap are skipped
+pub fn write_cells(state: &Window, cells: &HashMap<(usize, usize), Tile>) {
+    state.modify_tilemap_data(|tilemap| {
+        let mut before = HashMap::new();
+        let mut after = HashMap::new();
+        for (&(x, y), &tile) in cells {
+            if let Some(old) = tilemap.get((x, y)).filter(|old| *old != tile) {
+                before.insert((x, y), old);
+                after.insert((x, y), tile);
+                tilemap.tiles[y * tilemap.width + x] = tile;
+            }
+        }
+        if after.is_empty() {
+            // nothing changed
+            return false;
+        }
+        state.push_op(ChangeTilemapCells::new(before, after).into());
+        true
+    });
+}
+
+// cells changed by moving the tiles between corners from and to so the top left corner is at dest,
+// the cells left behind are cleared
+pub fn move_cells(
+    tilemap: &Tilemap,
+    (from, to): ((usize, usize), (usize, usize)),
+    dest: (usize, usize),
+) -> HashMap<(usize, usize), Tile> {
+    let mut cells = HashMap::new();
+    for y in from.1..=to.1 {
+        for x in from.0..=to.0 {
+            cells.insert((x, y), Tile::default());
+        }
+    }
+    cells.extend(tilemap.copy_rect(from, to).cells_at(dest));
+    cells
+}
@@ -0,0 +1,31 @@
+use gtk::cairo::{Context, Filter, Format, ImageSurface};
+
+use crate::data::image::Rgba;
+
+// paint an image from data::render with its top left corner at the origin,
+// each image pixel pxl_w wide
+pub fn paint_image(cr: &Context, image: &Rgba, pxl_w: f64) {
+    let Ok(mut surface) =
+        ImageSurface::create(Format::ARgb32, image.width as i32, image.height as i32)
+    else {
+        return;
+    };
+    let stride = surface.stride() as usize;
+    if let Ok(mut data) = surface.data() {
+        for (i, &[r, g, b, a]) in image.pixels.iter().enumerate() {
+            // native endian 0xAARRGGBB with premultiplied alpha
+            let pm = |c: u8| (c as u16 * a as u16 / 255) as u8;
+            let px = u32::from_be_bytes([a, pm(r), pm(g), pm(b)]);
+            let offset = (i / image.width) * stride + (i % image.width) * 4;
+            data[offset..offset + 4].copy_from_slice(&px.to_ne_bytes());
+        }
+    }
+
+    let _ = cr.save();
+    cr.scale(pxl_w, pxl_w);
+    let _ = cr.set_source_surface(&surface, 0.0, 0.0);
+    // keep pixels sharp when scaling up
+    cr.source().set_filter(Filter::Nearest);
+    let _ = cr.paint();
+    let _ = cr.restore();
+}
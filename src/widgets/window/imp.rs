@@ -15,19 +15,29 @@ use gtk::{glib, CompositeTemplate};
 
 use crate::data::{
     color::Color,
+    compression::Compression,
+    file_format::PaletteFile,
     list_items::{BGModeTwo, Bpp, TileSize},
     palette::Palette,
-    tilemap::Tilemap,
+    rom::RomRegion,
+    tilemap::{TileBlock, Tilemap},
     tiles::Tileset,
 };
 use crate::undo_stack::UndoStack;
+use crate::utils::{file_open_dialog, file_save_dialog};
 use crate::widgets::{
     color_picker::ColorPicker,
     palette_picker::{utils::unsaved_palette_dialog, PalettePicker},
-    tile_picker::TilePicker,
-    tilemap_editor::{utils::unsaved_tilemap_dialog, TilemapEditor},
+    tile_editor::TileEditor,
+    tile_picker::{utils::unsaved_tileset_dialog, TilePicker},
+    tilemap_editor::{
+        utils::{unsaved_all_dialog, unsaved_tilemap_dialog},
+        TilemapEditor,
+    },
 };
 
+use super::utils::{open_project, save_all};
+
 #[derive(CompositeTemplate, Properties, Default)]
 #[template(resource = "/com/example/waffle/window.ui")]
 #[properties(wrapper_type = super::Window)]
@@ -40,6 +50,8 @@ pub struct Window {
     pub tilemap_editor: TemplateChild<TilemapEditor>,
     #[template_child]
     pub tile_picker: TemplateChild<TilePicker>,
+    #[template_child]
+    pub tile_editor: TemplateChild<TileEditor>,
 
     // color picker properties
     #[property(get, set)]
@@ -51,6 +63,13 @@ pub struct Window {
     palette_sel_idx: Cell<u8>,
     #[property(get, set, nullable)]
     palette_file: RefCell<Option<PathBuf>>,
+    // format palette_file was opened with, and is saved back in
+    #[property(get, set, builder(PaletteFile::default()))]
+    palette_format: Cell<PaletteFile>,
+    #[property(get, set, builder(Compression::default()))]
+    palette_compression: Cell<Compression>,
+    // set if palette was opened from inside a rom
+    pub(super) palette_rom: RefCell<Option<RomRegion>>,
 
     // tile picker properties
     pub(super) tileset_data: RefCell<Tileset>,
@@ -58,11 +77,25 @@ pub struct Window {
     tileset_sel_idx: Cell<u32>,
     #[property(get, set, nullable)]
     tileset_file: RefCell<Option<PathBuf>>,
+    #[property(get, set, builder(Compression::default()))]
+    tileset_compression: Cell<Compression>,
+    // set if tileset was opened from inside a rom
+    pub(super) tileset_rom: RefCell<Option<RomRegion>>,
 
     // tilemap editor properties
     pub(super) tilemap_data: RefCell<Tilemap>,
     #[property(get, set, nullable)]
     tilemap_file: RefCell<Option<PathBuf>>,
+    #[property(get, set, builder(Compression::default()))]
+    tilemap_compression: Cell<Compression>,
+    // set if tilemap was opened from inside a rom
+    pub(super) tilemap_rom: RefCell<Option<RomRegion>>,
+    // block of tiles drawn instead of a single tile
+    pub(super) stamp: RefCell<Option<TileBlock>>,
+
+    // project the assets and view settings were opened from or saved to
+    #[property(get, set, nullable)]
+    project_file: RefCell<Option<PathBuf>>,
 
     #[property(get, set, builder(Bpp::default()))]
     pub tile_bpp: Cell<Bpp>,
@@ -70,6 +103,11 @@ pub struct Window {
     pub bg_mode: Cell<BGModeTwo>,
     #[property(get, set, builder(TileSize::default()))]
     pub tile_size: Cell<TileSize>,
+    // file format of each asset, saved the way it was opened
+    #[property(get, set)]
+    pub tileset_mode7: Cell<bool>,
+    #[property(get, set)]
+    pub tilemap_mode7: Cell<bool>,
 
     pub undo_stack: RefCell<UndoStack>,
 }
@@ -85,6 +123,7 @@ impl ObjectSubclass for Window {
         ColorPicker::ensure_type();
         PalettePicker::ensure_type();
         TilemapEditor::ensure_type();
+        TileEditor::ensure_type();
 
         klass.bind_template();
         klass.install_action("win.close", None, |window, _, _| window.close());
@@ -116,6 +155,9 @@ impl ObjectImpl for Window {
         self.tile_picker.handle_action(&obj);
         self.tile_picker.render_widget(&obj);
 
+        self.tile_editor.handle_action(&obj);
+        self.tile_editor.render_widget(&obj);
+
         self.tilemap_editor.handle_action(&obj);
         self.tilemap_editor.render_widget(&obj);
 
@@ -143,6 +185,17 @@ impl ObjectImpl for Window {
                 );
                 return Propagation::Stop;
             }
+            if win.tileset_dirty() {
+                unsaved_tileset_dialog(
+                    win,
+                    clone!(
+                        #[weak]
+                        win,
+                        move || win.close()
+                    ),
+                );
+                return Propagation::Stop;
+            }
             println!("quit program");
             Propagation::Proceed
         });
@@ -167,6 +220,54 @@ impl ObjectImpl for Window {
             .build();
         self.obj().add_action_entries([action_undo, action_redo]);
 
+        // project files
+        let action_open_project = ActionEntry::builder("openproject")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                obj,
+                move |_, _, _| {
+                    file_open_dialog(this.clone(), move |filepath| {
+                        unsaved_all_dialog(
+                            &this,
+                            clone!(
+                                #[weak]
+                                this,
+                                move || {
+                                    open_project(&this, filepath.clone());
+                                }
+                            ),
+                        );
+                    });
+                }
+            ))
+            .build();
+        let action_save_all = ActionEntry::builder("saveall")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                obj,
+                move |_, _, _| {
+                    save_all(&this);
+                }
+            ))
+            .build();
+        let action_save_project_as = ActionEntry::builder("saveprojectas")
+            .activate(clone!(
+                #[weak(rename_to = this)]
+                obj,
+                move |_, _, _| {
+                    file_save_dialog(&this.clone(), move |_, filepath| {
+                        this.set_project_file(Some(filepath));
+                        save_all(&this);
+                    });
+                }
+            ))
+            .build();
+        self.obj().add_action_entries([
+            action_open_project,
+            action_save_all,
+            action_save_project_as,
+        ]);
+
         // debug stuff
         let action_debug = ActionEntry::builder("printstuff")
             .activate(clone!(
@@ -174,9 +275,10 @@ impl ObjectImpl for Window {
                 obj,
                 move |_, _, _| {
                     println!(
-                        "palette dirty: {}, tilemap dirty: {}",
+                        "palette dirty: {}, tilemap dirty: {}, tileset dirty: {}",
                         this.palette_dirty(),
-                        this.tilemap_dirty()
+                        this.tilemap_dirty(),
+                        this.tileset_dirty()
                     );
                 }
             ))
@@ -193,6 +295,7 @@ impl ObjectImpl for Window {
                 Signal::builder("palette-data-changed").build(),
                 Signal::builder("tileset-data-changed").build(),
                 Signal::builder("tilemap-data-changed").build(),
+                Signal::builder("stamp-changed").build(),
             ]
         })
     }
@@ -1,4 +1,6 @@
+pub mod draw;
 mod imp;
+pub mod utils;
 
 use glib::ByteArray;
 use glib::Object;
@@ -10,7 +12,9 @@ use crate::data::{
     color::Color,
     list_items::{Bpp, TileSize},
     palette::Palette,
-    tilemap::{Tile, Tilemap},
+    render::{ColorZero, RenderOptions},
+    rom::RomRegion,
+    tilemap::{Tile, TileBlock, Tilemap},
     tiles::Tileset,
 };
 use crate::undo_stack::Operation;
@@ -89,9 +93,45 @@ impl Window {
             return None;
         });
     }
+
+    pub fn stamp(&self) -> Option<TileBlock> {
+        self.imp().stamp.borrow().clone()
+    }
+    pub fn set_stamp(&self, stamp: Option<TileBlock>) {
+        *self.imp().stamp.borrow_mut() = stamp;
+        self.emit_by_name::<()>("stamp-changed", &[]);
+    }
+    pub fn connect_stamp_notify(&self, f: impl Fn(&Self) + 'static) {
+        self.connect_local("stamp-changed", false, move |args| {
+            f(args[0].get().unwrap());
+            None
+        });
+    }
+
+    pub fn palette_rom(&self) -> Option<RomRegion> {
+        self.imp().palette_rom.borrow().clone()
+    }
+    pub fn set_palette_rom(&self, region: Option<RomRegion>) {
+        *self.imp().palette_rom.borrow_mut() = region;
+    }
+
+    pub fn tileset_rom(&self) -> Option<RomRegion> {
+        self.imp().tileset_rom.borrow().clone()
+    }
+    pub fn set_tileset_rom(&self, region: Option<RomRegion>) {
+        *self.imp().tileset_rom.borrow_mut() = region;
+    }
+
+    pub fn tilemap_rom(&self) -> Option<RomRegion> {
+        self.imp().tilemap_rom.borrow().clone()
+    }
+    pub fn set_tilemap_rom(&self, region: Option<RomRegion>) {
+        *self.imp().tilemap_rom.borrow_mut() = region;
+    }
+
     pub fn put_tile(&self, idx: usize, tile: &Tile) {
-        self.modify_tilemap_data(|Tilemap(map)| {
-            let Some(old_tile) = map.get_mut(idx) else {
+        self.modify_tilemap_data(|tilemap| {
+            let Some(old_tile) = tilemap.tiles.get_mut(idx) else {
                 return false;
             };
             if *old_tile != *tile {
@@ -139,19 +179,68 @@ impl Window {
     pub fn mark_palette_clean(&self) {
         self.imp().undo_stack.borrow_mut().mark_palette_clean()
     }
+    pub fn mark_palette_dirty(&self) {
+        self.imp().undo_stack.borrow_mut().mark_palette_dirty()
+    }
     pub fn tilemap_dirty(&self) -> bool {
         self.imp().undo_stack.borrow().tilemap_dirty()
     }
     pub fn mark_tilemap_clean(&self) {
         self.imp().undo_stack.borrow_mut().mark_tilemap_clean()
     }
+    pub fn mark_tilemap_dirty(&self) {
+        self.imp().undo_stack.borrow_mut().mark_tilemap_dirty()
+    }
+    pub fn tileset_dirty(&self) -> bool {
+        self.imp().undo_stack.borrow().tileset_dirty()
+    }
+    pub fn mark_tileset_clean(&self) {
+        self.imp().undo_stack.borrow_mut().mark_tileset_clean()
+    }
+    pub fn mark_tileset_dirty(&self) {
+        self.imp().undo_stack.borrow_mut().mark_tileset_dirty()
+    }
 
     // helpful functions
     // idx of palette 0 color 0
     pub fn palette_base(&self) -> u8 {
-        match self.tile_bpp() {
-            Bpp::Two => self.bg_mode().palette_offset(),
-            Bpp::Four => 0,
+        self.tile_bpp().palette_base(self.bg_mode())
+    }
+
+    // view tiles the way mode 7 shows them if either asset is mode 7 data
+    pub fn mode7(&self) -> bool {
+        self.tileset_mode7() || self.tilemap_mode7()
+    }
+
+    pub fn connect_mode7_notify(&self, f: impl Fn(&Self) + Clone + 'static) {
+        self.connect_tileset_mode7_notify(f.clone());
+        self.connect_tilemap_mode7_notify(f);
+    }
+
+    // idx of color 0 of the palette selected in palette picker, at the current bit depth
+    pub fn curr_palette_start(&self) -> u8 {
+        self.palette_start(self.tile_bpp()) as u8
+    }
+
+    // idx of color 0 of the palette selected in palette picker, at bit depth bpp
+    pub fn palette_start(&self, bpp: Bpp) -> usize {
+        let sel_idx = self.palette_sel_idx() as usize;
+        sel_idx - sel_idx % bpp.to_val() as usize
+    }
+
+    // how the editors draw tiles: color 0 like any other color, missing tiles in pink
+    pub fn render_options(&self, bpp: Bpp) -> RenderOptions {
+        RenderOptions {
+            bpp,
+            palette_base: self.palette_base() as usize,
+            // mode 7 only uses 8x8 tiles
+            tile_size: if self.mode7() {
+                TileSize::Eight
+            } else {
+                self.tile_size()
+            },
+            color_zero: ColorZero::Subpalette,
+            mark_missing: true,
         }
     }
 
@@ -166,6 +255,20 @@ impl Window {
 
     // palette selected in palette picker
     pub fn curr_palette(&self) -> u8 {
-        (self.palette_sel_idx().wrapping_sub(self.palette_base()) / self.tile_bpp().to_val()) % 8
+        let offset = self.palette_sel_idx().wrapping_sub(self.palette_base()) as u16;
+        (offset / self.tile_bpp().to_val()) as u8 % 8
+    }
+
+    // select the same color in another palette
+    pub fn set_curr_palette(&self, palette: u8) {
+        let bpp = self.tile_bpp();
+        // single 256-color palette
+        if bpp == Bpp::Eight {
+            return;
+        }
+        let colors = bpp.to_val();
+        let color = self.palette_sel_idx() as u16 % colors;
+        let idx = self.palette_base() as u16 + (palette % 8) as u16 * colors + color;
+        self.set_palette_sel_idx(idx as u8);
     }
 }
@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+
+use gtk::glib::{self, clone};
+use gtk::subclass::prelude::*;
+
+use crate::data::{
+    project::{Project, RomPlace},
+    rom::{AddressKind, RomRegion},
+};
+use crate::utils::*;
+use crate::widgets::{
+    palette_picker::utils as palette, tile_picker::utils as tiles,
+    tilemap_editor::utils as tilemap, window::Window,
+};
+
+// restore asset files and view settings
+pub fn open_project(state: &Window, filepath: PathBuf) {
+    match Project::from_file(&filepath).and_then(|project| load_project(state, project)) {
+        Ok(_) => {
+            println!("load project: {filepath:?}");
+            state.set_project_file(Some(filepath));
+        }
+        Err(e) => eprintln!("Error: {e}"),
+    }
+}
+
+// stop at the first asset that fails to open
+fn load_project(state: &Window, project: Project) -> std::io::Result<()> {
+    let imp = state.imp();
+    // compression is used when opening
+    state.set_palette_compression(project.palette_compression);
+    state.set_tileset_compression(project.tileset_compression);
+    state.set_tilemap_compression(project.tilemap_compression);
+
+    // assets read from a rom are opened from the same place again
+    let picker = &imp.tile_picker;
+    let (bpp, size, offset) = (project.tile_bpp, project.tilemap_size, AddressKind::Offset);
+    if let Some(file) = project.palette_file {
+        if let Some(rom) = project.palette_rom {
+            palette::open_rom(state, file, offset, rom.offset, rom.len)?;
+        } else {
+            palette::open_file(state, file, project.palette_format)?;
+        }
+    }
+    if let Some(file) = project.tileset_file {
+        if project.tileset_mode7 {
+            tilemap::open_mode7_tileset(state, file)?;
+        } else if let Some(rom) = project.tileset_rom {
+            tiles::open_rom(picker, state, file, bpp, offset, rom.offset, rom.len)?;
+        } else {
+            tiles::open_file(picker, state, file, bpp)?;
+        }
+    }
+    if let Some(file) = project.tilemap_file {
+        if project.tilemap_mode7 {
+            tilemap::open_mode7_tilemap(state, file)?;
+        } else if let Some(rom) = project.tilemap_rom {
+            tilemap::open_rom(state, file, size, offset, rom.offset, rom.len)?;
+        } else {
+            tilemap::open_file(state, file, size)?;
+        }
+    }
+
+    // mode 7 resets the tile size, so set view settings last
+    state.set_tile_bpp(project.tile_bpp);
+    state.set_tile_size(project.tile_size);
+    state.set_bg_mode(project.bg_mode);
+    imp.tilemap_editor.set_tilemap_zoom(project.zoom);
+    Ok(())
+}
+
+// the rom an asset was read from, if it is still saved there
+fn rom_place(region: Option<RomRegion>, file: Option<PathBuf>) -> Option<RomPlace> {
+    region
+        .filter(|r| Some(&r.path) == file.as_ref())
+        .map(|r| RomPlace {
+            offset: r.offset - r.header,
+            len: r.len,
+        })
+}
+
+pub fn save_project(state: &Window, filepath: PathBuf) {
+    let project = Project {
+        palette_file: state.palette_file(),
+        palette_format: state.palette_format(),
+        palette_compression: state.palette_compression(),
+        palette_rom: rom_place(state.palette_rom(), state.palette_file()),
+        tileset_file: state.tileset_file(),
+        tileset_compression: state.tileset_compression(),
+        tileset_rom: rom_place(state.tileset_rom(), state.tileset_file()),
+        tileset_mode7: state.tileset_mode7(),
+        tilemap_file: state.tilemap_file(),
+        tilemap_size: state.tilemap_data().size().unwrap_or_default(),
+        tilemap_compression: state.tilemap_compression(),
+        tilemap_rom: rom_place(state.tilemap_rom(), state.tilemap_file()),
+        tilemap_mode7: state.tilemap_mode7(),
+        tile_bpp: state.tile_bpp(),
+        tile_size: state.tile_size(),
+        bg_mode: state.bg_mode(),
+        zoom: state.imp().tilemap_editor.tilemap_zoom(),
+    };
+    match project.write_to_file(&filepath) {
+        Ok(_) => {
+            println!("save project: {filepath:?}");
+            state.set_project_file(Some(filepath));
+        }
+        Err(e) => eprintln!("Error saving file: {e}"),
+    }
+}
+
+// save an asset if dirty, asking for a file if it has none,
+// then run after unless saving failed
+fn save_step(
+    state: &Window,
+    dirty: bool,
+    file: Option<PathBuf>,
+    save: impl Fn(&Window, PathBuf) -> std::io::Result<()> + Clone + 'static,
+    after: impl Fn() + Clone + 'static,
+) -> impl Fn() + Clone + 'static {
+    clone!(
+        #[weak]
+        state,
+        move || {
+            if !dirty {
+                after();
+                return;
+            }
+            if let Some(filepath) = file.clone() {
+                match save(&state, filepath) {
+                    Ok(_) => after(),
+                    Err(e) => eprintln!("Error saving file: {e}"),
+                }
+            } else {
+                let (save, after) = (save.clone(), after.clone());
+                file_save_dialog(&state.clone(), move |_, filepath| {
+                    match save(&state, filepath) {
+                        Ok(_) => after(),
+                        Err(e) => eprintln!("Error saving file: {e}"),
+                    }
+                });
+            }
+        }
+    )
+}
+
+// save every dirty asset, then the project
+pub fn save_all(state: &Window) {
+    let after_assets = clone!(
+        #[weak]
+        state,
+        move || {
+            if let Some(filepath) = state.project_file() {
+                save_project(&state, filepath);
+            } else {
+                file_save_dialog(&state.clone(), move |_, filepath| {
+                    save_project(&state, filepath);
+                });
+            }
+        }
+    );
+    let after_tileset = save_step(
+        state,
+        state.tilemap_dirty(),
+        state.tilemap_file(),
+        tilemap::save_file,
+        after_assets,
+    );
+    let after_palette = save_step(
+        state,
+        state.tileset_dirty(),
+        state.tileset_file(),
+        tiles::save_file,
+        after_tileset,
+    );
+    save_step(
+        state,
+        state.palette_dirty(),
+        state.palette_file(),
+        |state: &Window, filepath| palette::save_file(state, filepath, state.palette_format()),
+        after_palette,
+    )();
+}